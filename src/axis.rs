@@ -0,0 +1,66 @@
+use crate::options::{Title, YAxis};
+
+/// Y-axis a series is plotted against, either by its position or by the name it was
+/// registered under with `FigureBuilder::add_y_axis`
+#[derive(Debug, Clone, PartialEq)]
+pub enum AxisRef {
+    Index(usize),
+    Name(String),
+}
+
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct AxisConfig {
+    pub title: Option<String>,
+    pub opposite: bool,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub logarithmic: bool,
+    pub log_base: Option<f64>,
+}
+
+impl AxisConfig {
+    pub fn new(title: impl Into<String>, opposite: bool) -> Self {
+        AxisConfig::default().with_title(title).with_opposite(opposite)
+    }
+
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    pub fn with_opposite(mut self, opposite: bool) -> Self {
+        self.opposite = opposite;
+        self
+    }
+
+    pub fn with_range(mut self, min: f64, max: f64) -> Self {
+        (self.min, self.max) = (Some(min), Some(max));
+        self
+    }
+
+    /// Logarithmic scale, zero and negative values are undefined just as in ApexCharts
+    pub fn with_logarithmic(mut self, logarithmic: bool) -> Self {
+        self.logarithmic = logarithmic;
+        self
+    }
+
+    /// Base of the logarithmic scale, ApexCharts defaults to 10
+    pub fn with_log_base(mut self, base: f64) -> Self {
+        self.log_base = Some(base);
+        self
+    }
+
+    pub(crate) fn options(&self, series_name: Option<&String>, show: bool) -> YAxis {
+        YAxis {
+            series_name: series_name.cloned(),
+            show,
+            opposite: self.opposite,
+            title: self.title.as_ref().map(Title::new),
+            min: self.min,
+            max: self.max,
+            logarithmic: self.logarithmic.then_some(true),
+            log_base: self.log_base,
+            labels: None,
+        }
+    }
+}
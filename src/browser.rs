@@ -0,0 +1,141 @@
+use std::path::{Path, PathBuf};
+use std::{env, io, process};
+
+struct Browser {
+    command: &'static str,
+    chromium: bool,
+    windows: &'static str,
+    macos: &'static str,
+}
+
+// Chromium-based browsers come first, since they can open figures in a chromeless app window
+#[rustfmt::skip]
+const BROWSERS: [Browser; 10] = [
+    Browser { command: "google-chrome", chromium: true, windows: r"Google\Chrome\Application\chrome.exe", macos: "Google Chrome" },
+    Browser { command: "chrome", chromium: true, windows: r"Google\Chrome\Application\chrome.exe", macos: "Google Chrome" },
+    Browser { command: "chromium", chromium: true, windows: r"Chromium\Application\chrome.exe", macos: "Chromium" },
+    Browser { command: "chromium-browser", chromium: true, windows: "", macos: "" },
+    Browser { command: "msedge", chromium: true, windows: r"Microsoft\Edge\Application\msedge.exe", macos: "Microsoft Edge" },
+    Browser { command: "microsoft-edge", chromium: true, windows: "", macos: "" },
+    Browser { command: "brave-browser", chromium: true, windows: r"BraveSoftware\Brave-Browser\Application\brave.exe", macos: "Brave Browser" },
+    Browser { command: "brave", chromium: true, windows: "", macos: "" },
+    Browser { command: "vivaldi", chromium: true, windows: r"Vivaldi\Application\vivaldi.exe", macos: "Vivaldi" },
+    Browser { command: "firefox", chromium: false, windows: r"Mozilla Firefox\firefox.exe", macos: "Firefox" },
+];
+
+/// Installed browsers, chromium-based first. Browsers are searched for in `PATH` and in the
+/// default install locations on windows and macos
+pub fn installed() -> Vec<PathBuf> {
+    let dirs: Vec<PathBuf> = env::var_os("PATH")
+        .map(|paths| env::split_paths(&paths).collect())
+        .unwrap_or_default();
+
+    let mut found: Vec<PathBuf> = vec![];
+    for browser in &BROWSERS {
+        let path = find_in(browser.command, &dirs).or_else(|| default_location(browser));
+        if let Some(path) = path.filter(|path| !found.contains(path)) {
+            found.push(path);
+        }
+    }
+    found
+}
+
+/// Environment variable with a browser command or path that figures are opened with instead
+/// of the detected browser, e.g. to pin one on CI
+pub const BROWSER_VAR: &str = "AXOPLOTL_BROWSER";
+
+/// Browser set with `AXOPLOTL_BROWSER`, if any
+pub fn from_env() -> Option<String> {
+    env::var(BROWSER_VAR).ok().filter(|browser| !browser.trim().is_empty())
+}
+
+/// Preferred installed browser
+pub fn detect() -> Option<PathBuf> {
+    installed().into_iter().next()
+}
+
+/// Commands of the known chromium-based browsers
+pub fn chromium_commands() -> impl Iterator<Item = &'static str> {
+    BROWSERS.iter().filter(|known| known.chromium).map(|known| known.command)
+}
+
+/// Whether the browser command or path refers to a chromium-based browser
+pub fn is_chromium(browser: impl AsRef<Path>) -> bool {
+    let name = stem(&browser.as_ref().to_string_lossy());
+    BROWSERS.iter().filter(|known| known.chromium).any(|known| {
+        [known.command, known.windows, known.macos]
+            .iter()
+            .any(|candidate| !candidate.is_empty() && stem(candidate) == name)
+    })
+}
+
+// Lowercase file name without extension, for both unix and windows paths
+fn stem(path: &str) -> String {
+    let name = path.rsplit(['/', '\\']).next().unwrap_or_default();
+    name.trim_end_matches(".exe").to_lowercase()
+}
+
+/// Opens the url with the given browser, in app mode for chromium-based browsers
+pub fn launch(browser: impl AsRef<Path>, url: &str) -> Result<(), io::Error> {
+    let mut command = process::Command::new(browser.as_ref());
+    match is_chromium(&browser) {
+        true => command.arg(format!("--app={url}")),
+        false => command.args(["-new-window", url]),
+    };
+    command.spawn().map(|_| ())
+}
+
+fn find_in(command: &str, dirs: &[PathBuf]) -> Option<PathBuf> {
+    let file_name = format!("{command}{}", env::consts::EXE_SUFFIX);
+    dirs.iter()
+        .map(|dir| dir.join(&file_name))
+        .find(|path| path.is_file())
+}
+
+fn default_location(browser: &Browser) -> Option<PathBuf> {
+    let candidates: Vec<PathBuf> = match env::consts::OS {
+        "windows" if !browser.windows.is_empty() => {
+            let roots = ["ProgramFiles", "ProgramFiles(x86)", "LocalAppData"];
+            roots
+                .iter()
+                .filter_map(env::var_os)
+                .map(|root| PathBuf::from(root).join(browser.windows))
+                .collect()
+        }
+        "macos" if !browser.macos.is_empty() => {
+            let app = format!("/Applications/{0}.app/Contents/MacOS/{0}", browser.macos);
+            vec![PathBuf::from(app)]
+        }
+        _ => vec![],
+    };
+    candidates.into_iter().find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn detection_test() {
+        let dir = env::temp_dir().join("axoplotl-browser-test");
+        fs::create_dir_all(&dir).unwrap();
+        let brave = dir.join(format!("brave{}", env::consts::EXE_SUFFIX));
+        fs::write(&brave, "").unwrap();
+
+        let dirs = [dir];
+        assert_eq!(find_in("brave", &dirs), Some(brave));
+        assert_eq!(find_in("vivaldi", &dirs), None);
+        fs::remove_dir_all(&dirs[0]).unwrap();
+    }
+
+    #[test]
+    fn chromium_test() {
+        assert!(is_chromium("brave"));
+        assert!(is_chromium("/usr/bin/google-chrome"));
+        assert!(is_chromium(r"C:\Program Files\Google\Chrome\Application\chrome.exe"));
+        assert!(is_chromium("/Applications/Vivaldi.app/Contents/MacOS/Vivaldi"));
+        assert!(!is_chromium("/usr/bin/firefox"));
+        assert!(!is_chromium("lynx"));
+    }
+}
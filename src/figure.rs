@@ -1,9 +1,14 @@
-use io::Write;
-use std::{env, fs, io, path};
+use std::collections::HashMap;
 
-use crate::webimg::WebImage;
+use crate::axis::{AxisConfig, AxisRef};
+use crate::webimg::{downscale, draw_scale_bar, percentile, ColorMap, WebFormat, WebImage};
+use crate::heatmap::Heatmap;
+use crate::pie::Pie;
+use crate::svg;
+use crate::options::{self, ChartOptions, HeatmapOptions, OneOrMany, PieOptions, SeriesData};
 use crate::{js, series::*};
-use image::RgbImage;
+use image::{ImageError, RgbImage};
+use serde::Serialize;
 
 const COLOR_PALLETS: [[&str; 5]; 10] = [
     ["#008ffb", "#00e396", "#feb019", "#ff4560", "#775dd0"],
@@ -18,23 +23,156 @@ const COLOR_PALLETS: [[&str; 5]; 10] = [
     ["#a300d6", "#7d02eb", "#5653fe", "#2983ff", "#00b1f2"],
 ];
 
+const DARK_BACKGROUND: &str = "#1e1e1e";
+// Height in pixels of the charts placed below the main one, such as the brush
+const LINKED_CHART_HEIGHT: usize = 150;
+// ApexCharts' own fill opacity, used for series without one when another series sets it
+const DEFAULT_FILL_OPACITY: f64 = 0.9;
+
+// Stands in for the element id until the html it is hashed from is complete
+const ID_PLACEHOLDER: &str = "axoplotl-chart-id";
+
+const CDN_SCRIPT: &str = "<script src='https://cdn.jsdelivr.net/npm/apexcharts'></script>";
+
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub enum Theme {
+    #[default]
+    Light,
+    Dark,
+}
+
+/// Axes that can be zoomed by selecting a region of the chart
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub enum ZoomMode {
+    None,
+    #[default]
+    X,
+    Y,
+    XY,
+}
+
+impl ZoomMode {
+    // Rescaling y to the visible data only applies when zooming along x
+    fn options(self) -> options::Zoom {
+        let (typ, enabled) = match self {
+            Self::None => ("x", false),
+            Self::X => ("x", true),
+            Self::Y => ("y", true),
+            Self::XY => ("xy", true),
+        };
+        let auto_scale_yaxis = matches!(self, Self::None | Self::X);
+        options::Zoom { typ: typ.to_string(), enabled, auto_scale_yaxis }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LegendPosition {
+    Top,
+    Bottom,
+    Left,
+    Right,
+    Hidden,
+}
+
+impl LegendPosition {
+    fn options(self) -> options::Legend {
+        let position = match self {
+            Self::Top => "top",
+            Self::Bottom => "bottom",
+            Self::Left => "left",
+            Self::Right => "right",
+            Self::Hidden => return options::Legend { show: false, position: None },
+        };
+        options::Legend { show: true, position: Some(position.to_string()) }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct FigureBuilder<T> {
     pub title: Option<String>,
     pub width: usize,
     pub height: usize,
 
-    palette: usize,
+    fixed_size: bool,
+    background: Option<String>,
+    chart: ChartConfig,
+    series: SeriesConfig,
+    image: ImageConfig,
     data: T,
 }
 
+// Options shared by every ApexCharts figure
+#[derive(Debug, Clone, PartialEq)]
+struct ChartConfig {
+    palette: usize,
+    custom_palette: Option<Vec<String>>,
+    responsive: Option<usize>,
+    animations: bool,
+    toolbar: bool,
+    export: Option<bool>,
+    minified: bool,
+    theme: Theme,
+    legend: Option<LegendPosition>,
+    subtitle: Option<String>,
+    data_labels: Option<bool>,
+}
+
+impl Default for ChartConfig {
+    fn default() -> Self {
+        Self {
+            palette: 0,
+            custom_palette: None,
+            responsive: None,
+            animations: true,
+            toolbar: true,
+            export: None,
+            minified: false,
+            theme: Theme::Light,
+            legend: None,
+            subtitle: None,
+            data_labels: None,
+        }
+    }
+}
+
+// Options of series figures only
+#[derive(Default, Debug, Clone, PartialEq)]
+struct SeriesConfig {
+    zoom: ZoomMode,
+    brush: bool,
+    horizontal_bars: bool,
+    stacked: bool,
+    data_label_formatter: Option<String>,
+    y_format: Option<usize>,
+    robust_y: bool,
+    grid: Option<bool>,
+    grid_color: Option<String>,
+    hlines: Vec<(f64, String)>,
+    vlines: Vec<(f64, String)>,
+    x_label: Option<String>,
+    y_axis: AxisConfig,
+    y_axes: Vec<(String, AxisConfig)>,
+}
+
+// Options of image figures only
+#[derive(Default, Debug, Clone, PartialEq)]
+struct ImageConfig {
+    scale_bar: Option<(f64, String)>,
+    max_dim: Option<u32>,
+    format: WebFormat,
+}
+
 impl<T: Default> Default for FigureBuilder<T> {
     fn default() -> Self {
         Self {
             title: None,
             width: 1280,
             height: 720,
-            palette: 0,
+            fixed_size: false,
+            background: None,
+            chart: ChartConfig::default(),
+            series: SeriesConfig::default(),
+            image: ImageConfig::default(),
             data: T::default(),
         }
     }
@@ -42,17 +180,57 @@ impl<T: Default> Default for FigureBuilder<T> {
 
 impl<T> FigureBuilder<T> {
     pub fn new(title: &str, width: usize, height: usize, data: T) -> Self {
-        Self {
-            title: Some(title.to_string()),
-            width,
-            height,
-            palette: 0,
-            data,
+        FigureBuilder::<()>::default()
+            .with_title(title)
+            .with_size(width, height)
+            .map_data(|_| data)
+    }
+
+    /// Picks one of the built-in palettes, replacing any custom palette
+    pub fn with_palette(mut self, palette: usize) -> Self {
+        self.chart.palette = palette % 10;
+        self.chart.custom_palette = None;
+        self
+    }
+
+    /// Colors assigned in turn to series without a color, pie slices and heatmap cells. An
+    /// empty list keeps the built-in palette
+    pub fn with_custom_palette(mut self, colors: &[&str]) -> Self {
+        let colors = colors.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+        self.chart.custom_palette = (!colors.is_empty()).then_some(colors);
+        self
+    }
+    
+    pub fn palette(&self) -> &[&str] {
+        COLOR_PALLETS[self.chart.palette].as_slice()
+    }
+
+    /// Colors actually used, the custom palette if set or else the built-in one
+    pub fn palette_colors(&self) -> Vec<&str> {
+        match &self.chart.custom_palette {
+            Some(colors) => colors.iter().map(String::as_str).collect(),
+            None => COLOR_PALLETS[self.chart.palette].to_vec(),
+        }
+    }
+
+    fn map_data<U>(self, f: impl FnOnce(T) -> U) -> FigureBuilder<U> {
+        FigureBuilder {
+            title: self.title,
+            width: self.width,
+            height: self.height,
+            fixed_size: self.fixed_size,
+            background: self.background,
+            chart: self.chart,
+            series: self.series,
+            image: self.image,
+            data: f(self.data),
         }
     }
     
+    /// Size of the figure in pixels. Charts without an explicit size fill 90% of the page
     pub fn with_size(mut self, width: usize, height: usize) -> Self {
         (self.width, self.height) = (width, height);
+        self.fixed_size = true;
         self
     }
     
@@ -60,9 +238,204 @@ impl<T> FigureBuilder<T> {
         self.title = Some(title.into());
         self
     }
+
+    pub fn with_subtitle(mut self, subtitle: impl Into<String>) -> Self {
+        self.chart.subtitle = Some(subtitle.into());
+        self
+    }
+
+    /// Dark theme also darkens the page around the chart, unless a background is set
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.chart.theme = theme;
+        self
+    }
+
+    /// Animates drawing the chart on load, enabled by default
+    pub fn with_animation(mut self, enabled: bool) -> Self {
+        self.chart.animations = enabled;
+        self
+    }
+
+    /// Shows or hides the chart toolbar, zoom is configured separately
+    pub fn with_toolbar(mut self, show: bool) -> Self {
+        self.chart.toolbar = show;
+        self
+    }
+
+    /// Shows or hides the toolbar menu exporting the chart as PNG, SVG or CSV
+    pub fn with_export(mut self, enabled: bool) -> Self {
+        self.chart.export = Some(enabled);
+        self
+    }
+
+    /// Disables animations, toolbar and zoom, forces light theme with a white background and
+    /// renders the chart at the exact builder size instead of filling the page
+    pub fn with_print_mode(mut self) -> Self {
+        (self.chart.animations, self.chart.toolbar, self.series.zoom) = (false, false, ZoomMode::None);
+        self.chart.theme = Theme::Light;
+        self.fixed_size = true;
+        self.background = Some("#ffffff".to_string());
+        self
+    }
+
+    /// Places or hides the legend, unnamed series are listed as `series-N`
+    pub fn with_legend(mut self, legend: LegendPosition) -> Self {
+        self.chart.legend = Some(legend);
+        self
+    }
+
+    /// Hides the legend and shrinks fonts when the chart is narrower than `breakpoint` pixels
+    pub fn with_responsive(mut self, breakpoint: usize) -> Self {
+        self.chart.responsive = Some(breakpoint);
+        self
+    }
     
-    fn stylesheet(id: &str) -> String {
-        format!("#{id} {{height: 100%; width: auto; padding: 0; margin: 0; display: flex; align-items: center; justify-content: center;}}")
+    /// Prints the values next to the data points, pie slices show their percentage unless
+    /// this is disabled
+    pub fn with_data_labels(mut self, enabled: bool) -> Self {
+        self.chart.data_labels = Some(enabled);
+        self
+    }
+
+    /// Embeds chart options without any indentation, which keeps the generated html small
+    pub fn with_minified(mut self, minified: bool) -> Self {
+        self.chart.minified = minified;
+        self
+    }
+
+    // Chart options shared by every ApexCharts figure, series charts add zoom and stacking
+    fn base_chart(&self, typ: &str) -> options::Chart {
+        let size = |pixels: usize| match self.fixed_size {
+            true => options::Size::Pixels(pixels),
+            false => options::Size::Css("90%".to_string()),
+        };
+        options::Chart {
+            id: None,
+            typ: typ.to_string(),
+            width: size(self.width),
+            height: size(self.height),
+            stacked: None,
+            background: self.background.clone(),
+            animations: (!self.chart.animations).then_some(options::Toggle { enabled: false }),
+            brush: None,
+            selection: None,
+            zoom: None,
+            toolbar: options::Toolbar {
+                show: self.chart.toolbar,
+                auto_selected: "zoom".to_string(),
+                tools: self.chart.export.map(|download| options::Tools { download }),
+            },
+        }
+    }
+
+    fn theme_options(&self) -> Option<options::Theme> {
+        (self.chart.theme == Theme::Dark).then(|| options::Theme { mode: "dark".to_string() })
+    }
+
+    fn responsive_options(&self) -> Option<Vec<options::Responsive>> {
+        let font_size = options::Styled::font_size;
+        let axis_labels = |size| options::Labels { labels: font_size(size) };
+        self.chart.responsive.map(|breakpoint| {
+            vec![options::Responsive {
+                breakpoint,
+                options: options::ResponsiveOptions {
+                    legend: options::Legend { show: false, position: None },
+                    title: font_size("12px"),
+                    xaxis: axis_labels("10px"),
+                    yaxis: axis_labels("10px"),
+                },
+            }]
+        })
+    }
+
+    fn render_options(&self, options: js::JS) -> String {
+        match self.chart.minified {
+            true => options.minify(),
+            false => options.pretty(),
+        }
+    }
+
+    // Replaces the id placeholder with an element id hashed from the html, so that building
+    // the same figure twice gives the same html while different figures don't collide
+    fn with_content_id(html: String) -> (String, String) {
+        // FNV-1a, which unlike `DefaultHasher` is stable across Rust releases
+        let hash = html.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+        let id = format!("chart-{:08x}", hash as u32);
+        (html.replace(ID_PLACEHOLDER, &id), id)
+    }
+
+    // ApexCharts has no stroke opacity option, the alpha goes into the stroke color instead
+    fn rgba(color: &str, alpha: f64) -> String {
+        let (r, g, b, _) = csscolorparser::parse(color).map(|c| c.rgba_u8()).unwrap_or_default();
+        format!("rgba({r}, {g}, {b}, {})", (alpha * 1000.0).round() / 1000.0)
+    }
+
+    // Page rendering the chart options, which are generated after the page head since
+    // generating them consumes the builder
+    fn chart_figure(&self) -> impl FnOnce(String) -> Figure {
+        let (_, charts) = self.charts_figure();
+        move |options| charts(vec![options])
+    }
+
+    // Same as `chart_figure` for a main chart followed by charts linked to it, each below
+    // the previous one. The main chart element id is returned for the linked charts to target
+    fn charts_figure(&self) -> (String, impl FnOnce(Vec<String>) -> Figure) {
+        let name = self.title.clone().unwrap_or_else(|| "figure".to_string());
+        let id = ID_PLACEHOLDER.to_string();
+        let dark = (self.chart.theme == Theme::Dark).then_some(DARK_BACKGROUND);
+        let background = self.background.clone().or(dark.map(str::to_string));
+        let runtime = CDN_SCRIPT.to_string();
+
+        let main = id.clone();
+        let figure = move |options: Vec<String>| {
+            let ids: Vec<String> = (0..options.len())
+                .map(|k| match k {
+                    0 => id.clone(),
+                    k => format!("{id}-{k}"),
+                })
+                .collect();
+            let mut css: Vec<String> = ids
+                .iter()
+                .map(|id| Self::stylesheet(id, background.as_deref()))
+                .collect();
+            if let [_, linked @ ..] = ids.as_slice() {
+                if !linked.is_empty() {
+                    let height = linked.len() * LINKED_CHART_HEIGHT;
+                    css.push(format!("#{id} {{height: calc(100% - {height}px);}}"));
+                    css.extend(linked.iter().map(|id| format!("#{id} {{height: {LINKED_CHART_HEIGHT}px;}}")));
+                }
+            }
+            let divs: Vec<String> = ids.iter().map(|id| format!("<div id='{id}'></div>")).collect();
+
+            #[rustfmt::skip]
+            let scripts: Vec<String> = ids.iter().zip(options).map(|(id, options)| format!(
+"{{
+    const options = {options};
+    const chart = new ApexCharts(document.querySelector('#{id}'), options);
+    chart.render();
+}}")).collect();
+
+            #[rustfmt::skip]
+            let body = format!(
+"<style>{}</style>
+{}
+<script>
+{}
+</script>", css.join("\n"), divs.join("\n"), scripts.join("\n"));
+            let (body, id) = Self::with_content_id(body);
+            let html = format!("{runtime}\n{body}");
+            Figure { name, html, image: None, id: Some(id), runtime: Some(runtime) }
+        };
+        (main, figure)
+    }
+
+    fn stylesheet(id: &str, background: Option<&str>) -> String {
+        let background = background
+            .map(|color| format!(" background: {color};"))
+            .unwrap_or_default();
+        format!("#{id} {{height: 100%; width: auto; padding: 0; margin: 0; display: flex; align-items: center; justify-content: center;{background}}}")
     }
 }
 
@@ -72,214 +445,978 @@ impl<Img: WebImage> FigureBuilder<Img> {
         self
     }
     
-    pub fn with_color_map(self, color_map: &str) -> FigureBuilder<RgbImage> {
-        FigureBuilder {
-            title: self.title,
-            width: self.width,
-            height: self.height,
-            palette: self.palette,
-            data: self.data.colormap(color_map),
+    pub fn with_color_map(self, color_map: ColorMap) -> FigureBuilder<RgbImage> {
+        self.map_data(|data| data.colormap(color_map))
+    }
+
+    /// Draws a scale bar into the bottom right corner of the image, see `draw_scale_bar`
+    ///
+    /// # Panics
+    /// When `pixels_per_unit` is not a finite positive number
+    pub fn with_scale_bar(mut self, pixels_per_unit: f64, unit: impl Into<String>) -> Self {
+        assert!(
+            pixels_per_unit.is_finite() && pixels_per_unit > 0.0,
+            "pixels per unit must be finite and positive, got {pixels_per_unit}"
+        );
+        self.image.scale_bar = Some((pixels_per_unit, unit.into()));
+        self
+    }
+
+    /// Format of the embedded image, `WebFormat::WebP` falls back to png when the `webp`
+    /// feature is disabled
+    pub fn with_format(mut self, format: WebFormat) -> Self {
+        self.image.format = format;
+        self
+    }
+
+    /// Css color shown behind transparent pixels, unset by default
+    pub fn with_background(mut self, color: impl Into<String>) -> Self {
+        self.background = Some(color.into());
+        self
+    }
+
+    /// Scales the embedded image down so that neither dimension exceeds `max` pixels
+    pub fn with_max_dim(mut self, max: u32) -> Self {
+        self.image.max_dim = Some(max);
+        self
+    }
+
+    fn encode64(&self) -> Result<String, ImageError> {
+        if self.image.scale_bar.is_none() && self.image.max_dim.is_none() {
+            return self.data.try_encode64_as(self.image.format.effective());
+        }
+        let original = self.data.to_rgba();
+        let mut image = match self.image.max_dim {
+            Some(max) => downscale(&original, max),
+            None => original.clone(),
+        };
+        if let Some((pixels_per_unit, unit)) = &self.image.scale_bar {
+            let scale = image.width() as f64 / original.width() as f64;
+            draw_scale_bar(&mut image, pixels_per_unit * scale, unit);
         }
+        image.try_encode64_as(self.image.format.effective())
     }
     
-    pub fn build(self) -> Figure {
-        let name = self.title.unwrap_or_else(|| "figure".to_string());
-        let id = "chart";
-        let css = Self::stylesheet(id);
+    pub fn try_build(self) -> Result<Figure, ImageError> {
+        let name = self.title.clone().unwrap_or_else(|| "figure".to_string());
+        let css = Self::stylesheet(ID_PLACEHOLDER, self.background.as_deref());
+        let image = self.encode64()?;
 
         #[rustfmt::skip]
         let html = format!(
 "<style>{css}</style>
-<img id='{id}' src='data:image/png;base64,{}'>", self.data.encode64());
-        Figure { name, html }
+<img id='{ID_PLACEHOLDER}' src='data:{};base64,{}'>", self.image.format.mime(), image);
+        let (html, id) = Self::with_content_id(html);
+        Ok(Figure { name, html, image: Some(image), id: Some(id), runtime: None })
+    }
+
+    /// # Panics
+    /// When the image can't be encoded, e.g. when it is empty, use `try_build` to handle the error
+    pub fn build(self) -> Figure {
+        self.try_build().unwrap()
     }
 }
 
+#[derive(Serialize)]
+struct Summary<'a> {
+    title: Option<&'a str>,
+    series_count: usize,
+    series: Vec<SeriesSummary<'a>>,
+}
+
+#[derive(Serialize)]
+struct SeriesSummary<'a> {
+    name: Option<&'a str>,
+    #[serde(rename = "type")]
+    typ: &'a str,
+    points: usize,
+}
+
 impl FigureBuilder<Vec<Series>> {
-    pub fn with_palette(mut self, palette: usize) -> Self {
-        self.palette = palette % 10;
-        self
+    // Colors for series without one. Once the built-in palette runs out the following
+    // palettes are used, so that colors only repeat after every distinct one was taken
+    fn auto_colors(&self) -> Vec<&str> {
+        if self.chart.custom_palette.is_some() {
+            return self.palette_colors();
+        }
+        let mut colors: Vec<&str> = vec![];
+        for i in 0..COLOR_PALLETS.len() {
+            for &color in &COLOR_PALLETS[(self.chart.palette + i) % COLOR_PALLETS.len()] {
+                if !colors.iter().any(|c| c.eq_ignore_ascii_case(color)) {
+                    colors.push(color);
+                }
+            }
+        }
+        colors
     }
     
-    pub fn palette(&self) -> &[&str] {
-        COLOR_PALLETS[self.palette].as_slice()
+    // Color of each series, its own, one picked from the palette or the next automatic one
+    fn series_colors(&self) -> Vec<String> {
+        let (palette, auto_colors) = (self.palette_colors(), self.auto_colors());
+        let mut color_gen = auto_colors.iter().copied().cycle();
+        self.data
+            .iter()
+            .map(|ser| {
+                let color = ser.style.to_apex_color(&palette);
+                color.unwrap_or_else(|| color_gen.next().unwrap().to_string())
+            })
+            .collect()
     }
-    
+
     pub fn with_series(mut self, series: Series) -> Self {
         self.data.push(series);
         self
     }
-    
-    fn generate_options(self) -> String {
-        let mut color_gen = (0..).map(|i| COLOR_PALLETS[self.palette][i % 5]);
+
+    /// Compact JSON description of the figure contents, without the series data itself
+    pub fn summary_json(&self) -> String {
+        let series: Vec<SeriesSummary> = self
+            .data
+            .iter()
+            .map(|ser| SeriesSummary { name: ser.name.as_deref(), typ: ser.style.typ(), points: ser.len() })
+            .collect();
+        let summary = Summary { title: self.title.as_deref(), series_count: series.len(), series };
+        serde_json::to_string(&summary).expect("summaries are always serializable")
+    }
+
+    /// Registers a named y-axis, series refer to it with `Series::with_y_axis_name` or by its
+    /// position with `Series::with_y_axis`. Series without an axis or with an unknown name are
+    /// plotted against the first one
+    pub fn add_y_axis(mut self, name: impl Into<String>, axis: AxisConfig) -> Self {
+        self.series.y_axes.push((name.into(), axis));
+        self
+    }
+
+    /// Logarithmic y-axis, zero and negative values are undefined just as in ApexCharts.
+    /// Applies to every axis not registered with `add_y_axis`
+    pub fn with_log_y(mut self, logarithmic: bool) -> Self {
+        self.series.y_axis.logarithmic = logarithmic;
+        self
+    }
+
+    /// Base of the logarithmic y-axis, 10 by default
+    pub fn with_log_base(mut self, base: f64) -> Self {
+        self.series.y_axis.log_base = Some(base);
+        self
+    }
+
+    /// Title of the x-axis, empty labels are omitted
+    pub fn with_x_label(mut self, label: impl Into<String>) -> Self {
+        let label = label.into();
+        self.series.x_label = (!label.is_empty()).then_some(label);
+        self
+    }
+
+    /// Title of the y-axis, empty labels are omitted
+    pub fn with_y_label(mut self, label: impl Into<String>) -> Self {
+        let label = label.into();
+        self.series.y_axis.title = (!label.is_empty()).then_some(label);
+        self
+    }
+
+    /// Pins the y-axis range instead of scaling it to the data
+    pub fn with_y_range(mut self, min: f64, max: f64) -> Self {
+        self.series.y_axis = self.series.y_axis.with_range(min, max);
+        self
+    }
+
+    /// Rounds y values to `decimals` places in tooltips and axis labels
+    pub fn with_y_format(mut self, decimals: usize) -> Self {
+        self.series.y_format = Some(decimals);
+        self
+    }
+
+    /// Fits the y-axis to the 1st to 99th percentile of all y values, so that a few outliers
+    /// don't flatten the rest of the data. Only the axis range leaves the outliers out, they
+    /// are still drawn and may extend past the plot area. A range set with `with_y_range` takes
+    /// precedence
+    pub fn with_robust_y(mut self, robust: bool) -> Self {
+        self.series.robust_y = robust;
+        self
+    }
+
+    // Default y-axis with the robust range filled in when it is enabled
+    fn base_y_axis(&self) -> AxisConfig {
+        let axis = self.series.y_axis.clone();
+        if !self.series.robust_y || axis.min.is_some() || axis.max.is_some() {
+            return axis;
+        }
+        let mut sorted: Vec<f64> = self
+            .data
+            .iter()
+            .flat_map(|ser| ser.data().into_iter().map(|[_, &y]| y))
+            .filter(|y| y.is_finite())
+            .collect();
+        if sorted.is_empty() {
+            return axis;
+        }
+        sorted.sort_by(f64::total_cmp);
+        axis.with_range(percentile(&sorted, 1.0), percentile(&sorted, 99.0))
+    }
+
+    // Gaps reach the formatter as null and are passed through untouched
+    fn y_formatter(&self) -> Option<options::Formatted> {
+        self.series.y_format.map(|decimals| options::Formatted {
+            formatter: options::Raw(format!("(val) => val == null ? val : Number(val).toFixed({decimals})")),
+        })
+    }
+
+    fn generate_y_axes(&self) -> Option<OneOrMany<options::YAxis>> {
+        let axis_index: Vec<usize> = self
+            .data
+            .iter()
+            .map(|ser| match ser.y_axis() {
+                Some(AxisRef::Index(index)) => *index,
+                Some(AxisRef::Name(name)) => self
+                    .series.y_axes
+                    .iter()
+                    .position(|(axis, _)| axis == name)
+                    .unwrap_or(0),
+                None => 0,
+            })
+            .collect();
+        let labels = |axis: options::YAxis| options::YAxis { labels: self.y_formatter(), ..axis };
+        let base = self.base_y_axis();
+        if self.series.y_axes.is_empty() && axis_index.iter().all(|&axis| axis == 0) {
+            let customized = base != AxisConfig::default() || self.series.y_format.is_some();
+            return customized.then(|| OneOrMany::One(labels(base.options(None, true))));
+        }
+
+        let yaxis = axis_index
+            .iter()
+            .enumerate()
+            .map(|(i, &axis)| {
+                let first = axis_index.iter().position(|&a| a == axis).unwrap();
+                let series_name = self.data[first].name.as_ref();
+                labels(match self.series.y_axes.get(axis) {
+                    Some((_, config)) => config.options(series_name, first == i),
+                    None => base
+                        .clone()
+                        .with_opposite(axis > 0)
+                        .options(series_name, first == i),
+                })
+            })
+            .collect();
+        Some(OneOrMany::Many(yaxis))
+    }
+
+    /// Zooming is along x by default, `ZoomMode::None` disables it
+    pub fn with_zoom(mut self, zoom: ZoomMode) -> Self {
+        self.series.zoom = zoom;
+        self
+    }
+
+    /// Adds a small overview chart below the main one, selecting a range on it zooms the main
+    /// chart to that range
+    pub fn with_brush(mut self, enabled: bool) -> Self {
+        self.series.brush = enabled;
+        self
+    }
+
+    /// Adds a labeled horizontal line at `y`
+    pub fn with_hline(mut self, y: f64, label: impl Into<String>) -> Self {
+        self.series.hlines.push((y, label.into()));
+        self
+    }
+
+    /// Adds a labeled vertical line at `x`
+    pub fn with_vline(mut self, x: f64, label: impl Into<String>) -> Self {
+        self.series.vlines.push((x, label.into()));
+        self
+    }
+
+    fn generate_annotations(&self) -> Option<options::Annotations> {
+        if self.series.hlines.is_empty() && self.series.vlines.is_empty() {
+            return None;
+        }
+        let label = |text: &String| options::Title::new(text);
+        Some(options::Annotations {
+            yaxis: self.series.hlines.iter().map(|(y, text)| options::YLine { y: *y, label: label(text) }).collect(),
+            xaxis: self.series.vlines.iter().map(|(x, text)| options::XLine { x: *x, label: label(text) }).collect(),
+        })
+    }
+
+    /// Stacks area and column series on top of each other instead of overlapping them.
+    /// Stacked areas are filled with solid colors, since gradients blend into the layer below
+    pub fn with_stacked(mut self, stacked: bool) -> Self {
+        self.series.stacked = stacked;
+        self
+    }
+
+    /// Shows or hides the background gridlines
+    pub fn with_grid(mut self, show: bool) -> Self {
+        self.series.grid = Some(show);
+        self
+    }
+
+    pub fn with_grid_color(mut self, color: impl Into<String>) -> Self {
+        self.series.grid_color = Some(color.into());
+        self
+    }
+
+    /// Formats data labels with a javascript function, e.g. `"(val) => val.toFixed(1)"`
+    pub fn with_data_label_formatter(mut self, formatter: impl Into<String>) -> Self {
+        self.series.data_label_formatter = Some(formatter.into());
+        self
+    }
+
+    /// Renders column series as horizontal bars
+    pub fn with_horizontal_bars(mut self, horizontal: bool) -> Self {
+        self.series.horizontal_bars = horizontal;
+        self
+    }
+
+    // Data points carrying their error range as a pair of goal markers, which ApexCharts only
+    // draws on columns
+    fn error_bars(data: Vec<[f64; 2]>, yerr: &[f64], color: &str) -> SeriesData {
+        let goal = |name: &str, value: f64| options::Goal {
+            name: name.to_string(),
+            value: value.is_finite().then_some(value),
+            stroke_color: color.to_string(),
+            stroke_height: 2,
+        };
+        let points = data
+            .into_iter()
+            .zip(yerr)
+            .map(|([x, y], err)| options::GoalPoint {
+                x,
+                y: y.is_finite().then_some(y),
+                goals: vec![goal("low", y - err), goal("high", y + err)],
+            })
+            .collect();
+        SeriesData::Goals(points)
+    }
+
+    // Non-finite values become null, which ApexCharts draws as a gap
+    fn points(data: Vec<[f64; 2]>) -> SeriesData {
+        let points = data.into_iter().map(|[x, y]| (x, y.is_finite().then_some(y)));
+        SeriesData::Points(points.collect())
+    }
+
+    fn colored_points(data: Vec<[f64; 2]>, colors: Vec<String>) -> SeriesData {
+        let points = data.into_iter().zip(colors).map(|([x, y], fill_color)| options::ColoredPoint {
+            x,
+            y: y.is_finite().then_some(y),
+            fill_color,
+        });
+        SeriesData::Colored(points.collect())
+    }
+
+    // Mixed charts take the type of their most filled series, per series types override it
+    fn chart_type(&self) -> &'static str {
+        let typ = |t| self.data.iter().map(|ser| ser.style.typ()).filter(|&typ| typ == t).count();
+        match (typ("area"), typ("column")) {
+            (0, columns) if columns > 0 && columns == self.data.len() => "bar",
+            (0, _) => "line",
+            _ => "area",
+        }
+    }
+
+    /// ApexCharts options of the figure, the model the embedded js and `options_json` are
+    /// serialized from
+    pub fn chart_options(&self) -> ChartOptions {
+        let datetime = self.data.iter().any(Series::time_x);
+        let toggle = |enabled| options::Toggle { enabled };
+
+        let series_colors = self.series_colors();
 
         let mut colors = vec![];
         let mut fill = vec![];
+        let mut opacity = vec![];
+        let mut stroke_colors = vec![];
 
         let mut series = vec![];
         let mut markers = (vec![], vec![], vec![], vec![]);
         let mut stroke = (vec![], vec![], vec![]);
 
-        for ser in self.data {
+        let mut discrete = vec![];
+
+        for (i, ser) in self.data.iter().cloned().enumerate() {
+            let ser = ser.cleaned();
             let (style, name) = (ser.style.clone(), ser.name.clone());
-            series.push(js!({
-                type: (style.typ()),
-                name: (name)?,
-                data: (ser.into_data())
-            }));
-            let c = style.color().unwrap_or_else(|| color_gen.next().unwrap());
+            let (len, endpoint) = (ser.len(), ser.endpoint_marker());
+            let c = series_colors[i].as_str();
+            let colored = style.is_scatter().then(|| ser.point_colors().map(<[String]>::to_vec));
+            let yerr = ser.yerr().filter(|_| style.typ() == "column").map(<[f64]>::to_vec);
+            let data = match (yerr, colored.flatten()) {
+                (Some(yerr), _) => Self::error_bars(ser.into_data(), &yerr, c),
+                (None, Some(colors)) => Self::colored_points(ser.into_data(), colors),
+                (None, None) => Self::points(ser.into_data()),
+            };
+            series.push(options::SeriesOptions { typ: style.typ().to_string(), name, data });
             colors.push(c.to_owned());
 
-            fill.push(if style.typ() != "area" {
+            fill.push(if style.typ() != "area" || self.series.stacked {
                 "solid"
             } else {
                 "gradient"
-            });
+            }.to_string());
+            opacity.push(style.fill_opacity());
+            stroke_colors.push(style.stroke_opacity().map(|alpha| Self::rgba(c, alpha)));
 
-            markers.0.push(style.marker.shape().to_owned());
-            markers.1.push(style.marker.size);
+            markers.0.push(style.marker.apex_shape().to_owned());
+            markers.1.push(if endpoint { 0 } else { style.marker.size });
             markers.2.push(if style.marker.filled { 1 } else { -1 });
             markers
                 .3
                 .push(if style.marker.filled { "#ffffff00" } else { c }.to_owned());
 
+            if endpoint && len > 0 {
+                discrete.push(options::DiscreteMarker {
+                    series_index: i,
+                    data_point_index: len - 1,
+                    fill_color: c.to_owned(),
+                    stroke_color: c.to_owned(),
+                    size: style.marker.size,
+                });
+            }
+
             stroke.0.push(style.stroke.curve().to_owned());
             stroke.1.push(style.stroke.width);
-            stroke.2.push(if style.stroke.dashed {
-                3 * style.stroke.width
-            } else {
-                0
-            });
+            stroke.2.push(style.stroke.dash_array());
         }
 
-        js!({
-            title: {
-                text: (self.title)?
-            },
-            chart: {
-                type: "area",
-                width: "90%",
-                height: "90%",
-                zoom: {
-                    type: "x",
-                    enabled: true,
-                    autoScaleYaxis: true
-                },
-                toolbar: {
-                    autoSelected: "zoom"
-                },
+        let opacity = opacity.iter().any(Option::is_some).then(|| {
+            let opacity = opacity.iter().map(|o| o.unwrap_or(DEFAULT_FILL_OPACITY));
+            opacity.collect::<Vec<_>>()
+        });
+        let stroke_colors = stroke_colors.iter().any(Option::is_some).then(|| {
+            let colors = stroke_colors.into_iter().zip(&colors).map(|(s, c)| s.unwrap_or_else(|| c.clone()));
+            colors.collect::<Vec<_>>()
+        });
+
+        ChartOptions {
+            title: options::Title { text: self.title.clone() },
+            subtitle: self.chart.subtitle.as_ref().map(options::Title::new),
+            chart: options::Chart {
+                stacked: self.series.stacked.then_some(true),
+                zoom: Some(self.series.zoom.options()),
+                ..self.base_chart(self.chart_type())
             },
-            series: series,
-            fill: {
-                type: fill
+            theme: self.theme_options(),
+            series,
+            fill: options::Fill { typ: fill, opacity },
+            colors,
+            markers: options::Markers {
+                shape: markers.0,
+                size: markers.1,
+                fill_opacity: markers.2,
+                stroke_colors: markers.3,
+                hover: options::MarkerHover { size_offset: 0 },
+                radius: 1,
+                discrete: (!discrete.is_empty()).then_some(discrete),
             },
-            colors: colors,
-            markers: {
-                shape: (markers.0),
-                size: (markers.1),
-                fillOpacity: (markers.2),
-                strokeColors: (markers.3),
-                hover: {
-                    sizeOffset: 0
-                },
-                radius: 1
+            stroke: options::Stroke {
+                curve: stroke.0,
+                width: stroke.1,
+                dash_array: stroke.2,
+                colors: stroke_colors,
+                line_cap: "square".to_string(),
             },
-            stroke: {
-                curve: (stroke.0),
-                width: (stroke.1),
-                dashArray: (stroke.2),
-                lineCap: "square",
+            data_labels: options::DataLabels {
+                enabled: self.chart.data_labels.unwrap_or(false),
+                formatter: self.series.data_label_formatter.clone().map(options::Raw),
             },
-            dataLabels: {
-                enabled: false,
+            grid: (self.series.grid.is_some() || self.series.grid_color.is_some()).then(|| options::Grid {
+                show: self.series.grid,
+                border_color: self.series.grid_color.clone(),
+            }),
+            legend: self.chart.legend.map(LegendPosition::options),
+            plot_options: self
+                .series.horizontal_bars
+                .then_some(options::PlotOptions { bar: options::Bar { horizontal: true } }),
+            xaxis: options::XAxis {
+                typ: if datetime { "datetime" } else { "numeric" }.to_string(),
+                tick_placement: "dataPoints".to_string(),
+                tooltip: toggle(false),
+                title: self.series.x_label.as_ref().map(options::Title::new),
             },
-            xaxis: {
-                type: "numeric",
-                tickPlacement: "dataPoints",
-                tooltip: {
-                    enabled: false,
-                },
-            }
-        })
-        .pretty()
+            yaxis: self.generate_y_axes(),
+            tooltip: (datetime || self.series.y_format.is_some()).then(|| options::Tooltip {
+                x: datetime.then(|| options::TooltipX { format: "dd MMM yyyy HH:mm:ss".to_string() }),
+                y: self.y_formatter(),
+            }),
+            annotations: self.generate_annotations(),
+            responsive: self.responsive_options(),
+        }
     }
-    
-    pub fn build(self) -> Figure {
-        let name = self.title.clone().unwrap_or_else(|| "figure".to_string());
-        let id = "chart";
-        let css = Self::stylesheet(id);
 
-        #[rustfmt::skip]
-        let html = format!(
-"<script src='https://cdn.jsdelivr.net/npm/apexcharts'></script>
-<style>{css}</style>
-<div id='{id}'></div>
-<script>
-    const options = {};
-    const chart = new ApexCharts(document.querySelector('#{id}'), options);
-    chart.render();
-</script>", self.generate_options());
+    fn generate_options(self) -> String {
+        self.render_options(self.chart_options().to_js())
+    }
+
+    // Overview of all series below the main chart, without labels, legend or markers
+    fn brush_options(&self, target: &str) -> ChartOptions {
+        let mut options = self.chart_options();
+        options.title = options::Title::default();
+        (options.subtitle, options.annotations, options.responsive) = (None, None, None);
+        options.chart.height = options::Size::Css("90%".to_string());
+        options.chart.brush = Some(options::Brush { target: target.to_string(), enabled: true });
+        options.chart.selection = Some(options::Toggle { enabled: true });
+        options.chart.zoom = Some(ZoomMode::None.options());
+        options.chart.toolbar = options::Toolbar {
+            show: false,
+            auto_selected: "selection".to_string(),
+            tools: None,
+        };
+        options.markers.size.iter_mut().for_each(|size| *size = 0);
+        options.markers.discrete = None;
+        options.data_labels = options::DataLabels { enabled: false, formatter: None };
+        options.legend = Some(LegendPosition::Hidden.options());
+        options.xaxis.title = None;
+        options.yaxis = Some(OneOrMany::One(options::YAxis {
+            series_name: None,
+            show: false,
+            opposite: false,
+            title: None,
+            min: None,
+            max: None,
+            logarithmic: None,
+            log_base: None,
+            labels: None,
+        }));
+        options
+    }
+
+    /// ApexCharts options as JSON, for embedding charts in other pages. Minified figures
+    /// produce compact JSON, and javascript callbacks such as formatters are kept as strings
+    pub fn options_json(&self) -> String {
+        let pretty = !self.chart.minified;
+        self.chart_options().to_js().json(pretty)
+    }
+    
+    // Name of the series at `index`, unnamed series are numbered from 1
+    fn series_name(&self, index: usize) -> String {
+        self.data[index].name.clone().unwrap_or_else(|| format!("series_{}", index + 1))
+    }
+
+    /// Minimal Vega-Lite spec of the chart, with the points of every series in a single data
+    /// table. Each series is a layer filtered on its name and drawn with its own mark, point for
+    /// scatter series and otherwise line, area or bar for columns, colored as in the ApexCharts
+    /// figure
+    pub fn to_vega_lite(&self) -> String {
+        let names: Vec<String> = (0..self.data.len()).map(|i| self.series_name(i)).collect();
+        let values: Vec<serde_json::Value> = self
+            .data
+            .iter()
+            .zip(&names)
+            .flat_map(|(ser, name)| {
+                ser.clone().cleaned().into_data().into_iter().map(move |[x, y]| {
+                    serde_json::json!({ "x": x, "y": y.is_finite().then_some(y), "series": name })
+                })
+            })
+            .collect();
+        let datetime = self.data.iter().any(Series::time_x);
+        let layers: Vec<serde_json::Value> = self
+            .data
+            .iter()
+            .zip(&names)
+            .map(|(ser, name)| {
+                let mark = match ser.style.typ() {
+                    _ if ser.style.is_scatter() => "point",
+                    "column" => "bar",
+                    typ => typ,
+                };
+                serde_json::json!({
+                    "transform": [{ "filter": { "field": "series", "equal": name } }],
+                    "mark": { "type": mark, "tooltip": true },
+                })
+            })
+            .collect();
+
+        let mut spec = serde_json::json!({
+            "$schema": "https://vega.github.io/schema/vega-lite/v5.json",
+            "width": self.width,
+            "height": self.height,
+            "data": { "values": values },
+            "layer": layers,
+            "encoding": {
+                "x": { "field": "x", "type": if datetime { "temporal" } else { "quantitative" } },
+                "y": { "field": "y", "type": "quantitative" },
+                "color": {
+                    "field": "series",
+                    "type": "nominal",
+                    "scale": { "domain": names, "range": self.series_colors() },
+                },
+            },
+        });
+        if let Some(title) = &self.title {
+            spec["title"] = title.as_str().into();
+        }
+        serde_json::to_string_pretty(&spec).unwrap()
+    }
+
+    /// Plotted data as csv. When no series repeats an x value the table is wide, with an x column
+    /// and a column per series where missing values are left empty, otherwise it is long, with
+    /// `series,x,y` rows. Unnamed series are called `series_N`
+    pub fn to_csv(&self) -> String {
+        let field = |text: &str| match text.contains([',', '"', '\n', '\r']) {
+            true => format!("\"{}\"", text.replace('"', "\"\"")),
+            false => text.to_string(),
+        };
+        let value = |v: f64| if v.is_finite() { v.to_string() } else { String::new() };
+        let names: Vec<String> = (0..self.data.len()).map(|i| field(&self.series_name(i))).collect();
+        let data: Vec<_> = self.data.iter().map(|ser| ser.clone().cleaned().into_data()).collect();
+
+        // x values are compared by their bits, with both zeros being the same
+        let key = |x: f64| if x == 0.0 { 0 } else { x.to_bits() };
+        let maps: Vec<HashMap<u64, f64>> = data
+            .iter()
+            .map(|points| points.iter().map(|&[x, y]| (key(x), y)).collect())
+            .collect();
+        let wide = maps.iter().zip(&data).all(|(map, points)| map.len() == points.len());
+
+        let mut csv = String::new();
+        if wide {
+            let mut xs: Vec<f64> = data.iter().flatten().map(|&[x, _]| x).collect();
+            xs.sort_by(f64::total_cmp);
+            xs.dedup_by(|a, b| key(*a) == key(*b));
+
+            csv += &format!("x,{}\n", names.join(","));
+            for x in xs {
+                let ys = maps.iter().map(|map| map.get(&key(x)).map_or(String::new(), |&y| value(y)));
+                csv += &format!("{},{}\n", value(x), ys.collect::<Vec<_>>().join(","));
+            }
+        } else {
+            csv += "series,x,y\n";
+            for (name, points) in names.iter().zip(&data) {
+                for &[x, y] in points {
+                    csv += &format!("{name},{},{}\n", value(x), value(y));
+                }
+            }
+        }
+        csv
+    }
 
-        Figure { name, html }
+    /// Static svg of the chart drawn without ApexCharts, e.g. for reports. Only line and scatter
+    /// series are drawn faithfully, smooth curves are drawn straight and areas and columns as lines
+    pub fn build_svg(&self) -> String {
+        let colors = self.series_colors();
+        let series: Vec<_> = self
+            .data
+            .iter()
+            .map(|ser| ser.clone().cleaned())
+            .zip(colors.iter().map(String::as_str))
+            .collect();
+        let dark = self.chart.theme == Theme::Dark;
+        let background = self.background.as_deref().or(dark.then_some(DARK_BACKGROUND));
+        let ink = if dark { "#cccccc" } else { "#333333" };
+        svg::render(self.title.as_deref(), (self.width, self.height), background, ink, &series)
+    }
+
+    /// # Panics
+    /// When a series is invalid, e.g. x and y have different lengths, see `Series::validate`
+    pub fn build(self) -> Figure {
+        if !self.series.brush {
+            let chart = self.chart_figure();
+            return chart(self.generate_options());
+        }
+        let (id, charts) = self.charts_figure();
+        let mut main = self.chart_options();
+        main.chart.id = Some(id.clone());
+        let brush = self.brush_options(&id);
+        charts(vec![self.render_options(main.to_js()), self.render_options(brush.to_js())])
     }
 }
 
-#[derive(PartialEq)]
-pub struct Figure {
-    name: String,
-    html: String,
+impl FigureBuilder<Pie> {
+    pub fn with_slice(mut self, label: impl Into<String>, value: impl Into<f64>) -> Self {
+        self.data = self.data.with_slice(label, value);
+        self
+    }
+
+    /// Draws the slices as a ring instead of a full pie
+    pub fn with_donut(mut self, donut: bool) -> Self {
+        self.data.donut = donut;
+        self
+    }
+
+    pub fn chart_options(&self) -> PieOptions {
+        let chart_type = if self.data.donut { "donut" } else { "pie" };
+        let (labels, series): (Vec<String>, Vec<f64>) = self.data.slices().iter().cloned().unzip();
+        let palette = self.palette_colors();
+        let colors = (0..labels.len()).map(|i| palette[i % palette.len()].to_string()).collect();
+
+        PieOptions {
+            title: options::Title { text: self.title.clone() },
+            subtitle: self.chart.subtitle.as_ref().map(options::Title::new),
+            chart: self.base_chart(chart_type),
+            theme: self.theme_options(),
+            series,
+            labels,
+            colors,
+            data_labels: self.chart.data_labels.map(|enabled| options::DataLabels {
+                enabled,
+                formatter: None,
+            }),
+            legend: self.chart.legend.map(LegendPosition::options),
+            responsive: self.responsive_options(),
+        }
+    }
+
+    fn generate_options(self) -> String {
+        self.render_options(options::to_js(&self.chart_options()))
+    }
+
+    pub fn build(self) -> Figure {
+        let chart = self.chart_figure();
+        chart(self.generate_options())
+    }
 }
 
-impl Figure {
-    pub fn save_to(&self, directory: impl AsRef<path::Path>) -> Result<path::PathBuf, io::Error> {
-        if !directory.as_ref().is_dir() {
-            return Err(io::Error::new(io::ErrorKind::InvalidInput, "oh no!"));
+// Number of equal value ranges a color map is split into for heatmap cells
+const HEATMAP_RANGES: usize = 8;
+
+impl FigureBuilder<Heatmap> {
+    /// Colors cells by sampling the color map over the range of values, instead of shading
+    /// the first palette color
+    pub fn with_color_map(mut self, color_map: ColorMap) -> Self {
+        self.data.color_map = Some(color_map);
+        self
+    }
+
+    fn color_scale(&self) -> Option<options::HeatmapPlotOptions> {
+        let gradient = self.data.color_map.as_ref()?.gradient();
+        let values = self.data.values().iter().flatten().filter(|v| v.is_finite());
+        let (min, max) = values.fold((f64::MAX, f64::MIN), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+        if min > max {
+            return None;
         }
-        let file_name = format!(
-            "{}-{}.html",
-            self.name,
-            chrono::Local::now().format("%H%M%S")
-        );
-        let path = directory.as_ref().join(file_name);
+        let step = (max - min) / HEATMAP_RANGES as f64;
+        let ranges = (0..HEATMAP_RANGES)
+            .map(|i| options::ColorRange {
+                from: min + step * i as f64,
+                to: min + step * (i + 1) as f64,
+                color: gradient.at((i as f64 + 0.5) / HEATMAP_RANGES as f64).to_hex_string(),
+            })
+            .collect();
+        let color_scale = options::ColorScale { ranges };
+        Some(options::HeatmapPlotOptions { heatmap: options::HeatmapPlot { color_scale } })
+    }
+
+    pub fn chart_options(&self) -> HeatmapOptions {
+        let heatmap = &self.data;
+        let series = heatmap
+            .values()
+            .iter()
+            .enumerate()
+            .map(|(row, values)| {
+                let data = values
+                    .iter()
+                    .enumerate()
+                    .map(|(col, &y)| options::HeatmapCell {
+                        x: heatmap.col_label(col),
+                        y: y.is_finite().then_some(y),
+                    })
+                    .collect();
+                options::HeatmapRow { name: heatmap.row_label(row), data }
+            })
+            .collect();
 
-        let mut file = fs::File::create(&path)?;
-        file.write_all(self.html.as_bytes())?;
-        Ok(path)
+        HeatmapOptions {
+            title: options::Title { text: self.title.clone() },
+            subtitle: self.chart.subtitle.as_ref().map(options::Title::new),
+            chart: self.base_chart("heatmap"),
+            theme: self.theme_options(),
+            series,
+            colors: vec![self.palette_colors()[0].to_string()],
+            data_labels: options::DataLabels {
+                enabled: self.chart.data_labels.unwrap_or(false),
+                formatter: None,
+            },
+            legend: self.chart.legend.map(LegendPosition::options),
+            plot_options: self.color_scale(),
+            responsive: self.responsive_options(),
+        }
     }
-    
-    pub fn save(&self) -> Result<path::PathBuf, io::Error> {
-        self.save_to(&env::current_dir()?)
+
+    fn generate_options(self) -> String {
+        self.render_options(options::to_js(&self.chart_options()))
     }
-    
-    fn webview(path: impl AsRef<path::Path>) -> Result<(), io::Error> {
-        let path = path.as_ref().display();
-        let config = |browser| match browser {
-            "chrome" | "msedge" => format!("--app=file:///{}", path),
-            "firefox" => format!("-new-window file:///{}", path),
-            _ => unreachable!(),
+
+    pub fn build(self) -> Figure {
+        let chart = self.chart_figure();
+        chart(self.generate_options())
+    }
+}
+
+#[cfg(feature = "csv")]
+impl FigureBuilder<Vec<Series>> {
+    /// Reads a csv file with a header row and plots each of `y_cols` columns against `x_col`
+    pub fn from_csv(
+        path: impl AsRef<std::path::Path>,
+        x_col: &str,
+        y_cols: &[&str],
+    ) -> Result<Self, std::io::Error> {
+        let file = path.as_ref().display();
+        let invalid = |msg: String| std::io::Error::new(std::io::ErrorKind::InvalidData, msg);
+
+        let mut reader = csv::Reader::from_path(path.as_ref())?;
+        let headers = reader.headers()?.clone();
+        if headers.iter().all(|h| h.trim().parse::<f64>().is_ok()) {
+            return Err(invalid(format!("{file} does not start with a header row")));
+        }
+        let column = |name: &str| {
+            headers
+                .iter()
+                .position(|h| h.trim() == name)
+                .ok_or_else(|| invalid(format!("column '{name}' not found in header of {file}")))
         };
+        let x_idx = column(x_col)?;
+        let y_idx = y_cols.iter().map(|name| column(name)).collect::<Result<Vec<_>, _>>()?;
 
-        // TODO: detect installed browsers and choose the best one
-        // chromium-based browsers support app view so they are preferable
-        for browser in ["chrome", "msedge", "firefox"] {
-            if open::with(config(browser), browser).is_ok() {
-                return Ok(());
+        let (mut x, mut ys) = (vec![], vec![vec![]; y_cols.len()]);
+        for record in reader.records() {
+            let record = record?;
+            let line = record.position().map_or(0, |pos| pos.line());
+            let parse = |idx: usize, name: &str| {
+                let field = record.get(idx).unwrap_or_default();
+                field.trim().parse::<f64>().map_err(|_| {
+                    invalid(format!("cannot parse '{field}' in column '{name}' at {file}:{line} as a number"))
+                })
+            };
+            x.push(parse(x_idx, x_col)?);
+            for ((y, &idx), name) in ys.iter_mut().zip(&y_idx).zip(y_cols) {
+                y.push(parse(idx, name)?);
             }
         }
-        open::that(format!("file:///{}", path))
-    }
-    pub fn open(&self) -> Result<(), io::Error> {
-        let path = self.save_to(&env::temp_dir())?;
-        let result = Self::webview(&path);
-        if result.is_ok() {
-            println!("Press enter to continue...");
-            io::stdin().read_line(&mut String::new())?;
+
+        let fig = y_cols.iter().zip(ys).fold(Self::default(), |fig, (name, y)| {
+            fig.with_series(Series::new(&x, &y).with_name(*name))
+        });
+        Ok(fig)
+    }
+}
+
+/// Lays out multiple figures on a single page, filling the grid row by row
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grid {
+    pub title: Option<String>,
+    rows: usize,
+    cols: usize,
+    figures: Vec<Figure>,
+}
+
+impl Grid {
+    pub fn new(rows: usize, cols: usize) -> Self {
+        Grid { title: None, rows, cols, figures: vec![] }
+    }
+
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    pub fn with_figure(mut self, figure: Figure) -> Self {
+        self.figures.push(figure);
+        self
+    }
+
+    /// Cells share a single ApexCharts runtime, and their element ids are numbered by cell so
+    /// that the same figure can be placed more than once
+    pub fn build(self) -> Figure {
+        let name = self.title.unwrap_or_else(|| "grid".to_string());
+        let (rows, cols) = (self.rows.max(1), self.cols.max(1));
+        let mut runtimes: Vec<String> = vec![];
+        let mut cells: Vec<String> = vec![];
+        for (k, fig) in self.figures.iter().enumerate() {
+            let mut body = fig.html.as_str();
+            if let Some(runtime) = &fig.runtime {
+                body = body.strip_prefix(runtime.as_str()).unwrap_or(body).trim_start();
+                if !runtimes.contains(runtime) {
+                    runtimes.push(runtime.clone());
+                }
+            }
+            let body = match &fig.id {
+                Some(id) => body.replace(id.as_str(), &format!("{id}-cell{k}")),
+                None => body.to_string(),
+            };
+            cells.push(format!("<div class='axoplotl-cell'>\n{body}\n</div>"));
         }
-        fs::remove_file(path)?;
-        result
+        let runtime = (!runtimes.is_empty()).then(|| runtimes.join("\n"));
+
+        #[rustfmt::skip]
+        let body = format!(
+"<style>
+html, body {{height: 100%; margin: 0;}}
+.axoplotl-grid {{height: 100%; display: grid; grid-template-columns: repeat({cols}, 1fr); grid-template-rows: repeat({rows}, 1fr);}}
+.axoplotl-cell {{min-width: 0; min-height: 0; overflow: hidden;}}
+</style>
+<div class='axoplotl-grid'>
+{}
+</div>", cells.join("\n"));
+        let html = match &runtime {
+            Some(runtime) => format!("{runtime}\n{body}"),
+            None => body,
+        };
+
+        Figure { name, html, image: None, id: None, runtime }
     }
 }
 
+/// An image next to a series chart on one page, e.g. an image and a plot of its row averages
+#[derive(Debug, Clone)]
+pub struct Composite<Img: WebImage> {
+    pub title: Option<String>,
+    image: FigureBuilder<Img>,
+    chart: FigureBuilder<Vec<Series>>,
+}
+
+impl<Img: WebImage> Composite<Img> {
+    pub fn new(image: FigureBuilder<Img>, chart: FigureBuilder<Vec<Series>>) -> Self {
+        Composite { title: None, image, chart }
+    }
+
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    pub fn try_build(self) -> Result<Figure, ImageError> {
+        let grid = Grid::new(1, 2)
+            .with_title(self.title.unwrap_or_else(|| "composite".to_string()))
+            .with_figure(self.image.try_build()?)
+            .with_figure(self.chart.build());
+        Ok(grid.build())
+    }
+
+    /// # Panics
+    /// When the image can't be encoded, use `try_build` to handle the error
+    pub fn build(self) -> Figure {
+        self.try_build().unwrap()
+    }
+}
+
+#[cfg(feature = "io")]
+pub use crate::output::OpenOptions;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Figure {
+    pub(crate) name: String,
+    pub(crate) html: String,
+    pub(crate) image: Option<String>,
+    // Element id of the chart or image, the html contains it after any runtime script
+    pub(crate) id: Option<String>,
+    // Script tag loading ApexCharts that starts the html, shared by grid cells
+    pub(crate) runtime: Option<String>,
+}
+
+impl Figure {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Complete html page of the figure, e.g. for serving it from a web server. Charts load
+    /// ApexCharts from a cdn, image figures need no script
+    pub fn html(&self) -> &str {
+        &self.html
+    }
+}
+
+/// Builds a figure with one series per `;` separated group of `series!` arguments, e.g.
+/// `plot!(y1, "r"; x, y2; (x.iter().map(|x| x * 2.0).collect::<Vec<_>>(), y3, "@"))`. Series
+/// without x values are plotted against their indices, and `Style` values are passed in a
+/// tuple, like `plot!((y, style = my_style))`
 #[macro_export]
 macro_rules! plot {
     ($ ($ ($token:tt), *); *) => {{
@@ -291,6 +1428,8 @@ macro_rules! plot {
     }};
 }
 
+/// Displays an image, optionally colored with a color map given by name such as
+/// `imshow!(image, "magma")`. Unknown color map names panic
 #[macro_export]
 macro_rules! imshow {
     ($image:ident) => {
@@ -304,6 +1443,7 @@ macro_rules! imshow {
     };
 
     ($image:ident, $color_map:literal) => {{
+        #[allow(unused_imports)]
         use image::GenericImageView;
         let (w, h) = $image.dimensions();
         $crate::figure::FigureBuilder::new(
@@ -312,7 +1452,1079 @@ macro_rules! imshow {
             h as usize,
             &$image,
         )
-        .with_color_map($color_map)
+        .with_color_map($color_map.parse().unwrap_or_else(|err| panic!("{err}")))
         .build()
     }};
+
+    ($image:ident, max_dim = $max:expr) => {{
+        #[allow(unused_imports)]
+        use image::GenericImageView;
+        let (w, h) = $image.dimensions();
+        $crate::figure::FigureBuilder::new(
+            stringify!($image),
+            w as usize,
+            h as usize,
+            &$image,
+        )
+        .with_max_dim($max)
+        .build()
+    }};
+
+    ($image:ident, upscale = $factor:expr) => {{
+        let upscaled = $crate::webimg::WebImage::upscale_nearest(&$image, $factor);
+        $crate::figure::FigureBuilder::new(
+            stringify!($image),
+            upscaled.width() as usize,
+            upscaled.height() as usize,
+            upscaled,
+        )
+        .build()
+    }};
+
+    ($image:ident, $color_map:literal, max_dim = $max:expr) => {{
+        #[allow(unused_imports)]
+        use image::GenericImageView;
+        let (w, h) = $image.dimensions();
+        $crate::figure::FigureBuilder::new(
+            stringify!($image),
+            w as usize,
+            h as usize,
+            &$image,
+        )
+        .with_color_map($color_map.parse().unwrap_or_else(|err| panic!("{err}")))
+        .with_max_dim($max)
+        .build()
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::style::{Stroke, Style};
+
+    fn figure() -> FigureBuilder<Vec<Series>> {
+        FigureBuilder::default().with_series(Series::new(&[0, 1], &[1, 2]))
+    }
+
+    fn compact(options: String) -> String {
+        options.replace(char::is_whitespace, "")
+    }
+
+    #[test]
+    fn print_mode_test() {
+        let options = compact(figure().generate_options());
+        assert!(options.contains("toolbar:{show:true"));
+        assert!(!options.contains("animations"));
+
+        let options = compact(figure().with_size(800, 600).with_print_mode().generate_options());
+        assert!(options.contains("toolbar:{show:false"));
+        assert!(options.contains("animations:{enabled:false}"));
+        assert!(options.contains("zoom:{type:'x',enabled:false"));
+        assert!(options.contains("width:800,height:600,background:'#ffffff'"));
+    }
+
+    #[test]
+    fn animation_test() {
+        let options = figure().with_animation(false).chart_options();
+        assert_eq!(options.chart.animations, Some(options::Toggle { enabled: false }));
+        let options = compact(figure().with_animation(false).generate_options());
+        assert!(options.contains("animations:{enabled:false}"));
+        assert!(!compact(figure().with_animation(true).generate_options()).contains("animations"));
+    }
+
+    #[test]
+    fn y_format_test() {
+        let options = compact(figure().with_y_format(2).generate_options());
+        assert!(options.contains("tooltip:{y:{formatter:(val)=>val==null?val:Number(val).toFixed(2)}}"));
+        assert!(options.contains("yaxis:{show:true,opposite:false,labels:{formatter:(val)=>val==null?val:Number(val).toFixed(2)}}"));
+
+        let builder = figure().with_series(Series::new(&[0, 1], &[3, 4]).with_y_axis(1));
+        let options = compact(builder.with_y_format(0).generate_options());
+        assert_eq!(options.matches("toFixed(0)").count(), 3);
+        assert!(!compact(figure().generate_options()).contains("toFixed"));
+    }
+
+    #[test]
+    fn brush_test() {
+        let html = figure().with_title("main").with_minified(true).build().html;
+        assert_eq!(html.matches("new ApexCharts").count(), 1);
+        assert!(!html.contains("brush"));
+
+        let html = figure().with_title("main").with_brush(true).with_minified(true).build().html;
+        let id = html.split("<div id='").nth(1).unwrap().split('\'').next().unwrap();
+        assert!(html.contains(&format!("<div id='{id}'></div>\n<div id='{id}-1'></div>")));
+        assert!(html.contains(&format!("document.querySelector('#{id}-1')")));
+        assert_eq!(html.matches("const options = {").count(), 2);
+        assert!(html.contains(&format!("chart:{{id:'{id}',type:'line'")));
+        assert!(html.contains(&format!("brush:{{target:'{id}',enabled:true}},selection:{{enabled:true}}")));
+        assert!(html.contains(&format!("#{id}-1 {{height: {LINKED_CHART_HEIGHT}px;}}")));
+    }
+
+    #[test]
+    fn toolbar_test() {
+        let options = compact(figure().with_toolbar(false).generate_options());
+        assert!(options.contains("zoom:{type:'x',enabled:true"));
+        assert!(options.contains("toolbar:{show:false,autoSelected:'zoom'}"));
+
+        let options = compact(figure().with_export(true).generate_options());
+        assert!(options.contains("toolbar:{show:true,autoSelected:'zoom',tools:{download:true}}"));
+    }
+
+    #[test]
+    fn zoom_test() {
+        let options = compact(figure().generate_options());
+        assert!(options.contains("zoom:{type:'x',enabled:true,autoScaleYaxis:true}"));
+
+        let options = compact(figure().with_zoom(ZoomMode::None).generate_options());
+        assert!(options.contains("zoom:{type:'x',enabled:false,autoScaleYaxis:true}"));
+
+        let options = compact(figure().with_zoom(ZoomMode::XY).generate_options());
+        assert!(options.contains("zoom:{type:'xy',enabled:true,autoScaleYaxis:false}"));
+    }
+
+    #[test]
+    fn pie_test() {
+        let pie = FigureBuilder::new("Fruit", 400, 400, Pie::default())
+            .with_slice("apples", 3)
+            .with_slice("pears", 2)
+            .with_slice("plums", 1.5);
+        let options = compact(pie.clone().generate_options());
+        assert!(options.contains("chart:{type:'pie',"));
+        assert!(options.contains("series:[3.0,2.0,1.5],labels:['apples','pears','plums'],"));
+        assert!(options.contains("colors:['#008ffb','#00e396','#feb019']"));
+
+        let options = compact(pie.clone().with_donut(true).generate_options());
+        assert!(options.contains("chart:{type:'donut',"));
+
+        let custom = pie.clone().with_custom_palette(&["#111111", "#222222"]);
+        let options = compact(custom.generate_options());
+        assert!(options.contains("colors:['#111111','#222222','#111111']"));
+
+        let dark = pie
+            .with_theme(Theme::Dark)
+            .with_legend(LegendPosition::Hidden)
+            .with_data_labels(false);
+        let json = serde_json::to_value(dark.chart_options()).unwrap();
+        assert_eq!(json["theme"], serde_json::json!({ "mode": "dark" }));
+        assert_eq!(json["legend"], serde_json::json!({ "show": false }));
+        assert_eq!(json["dataLabels"], serde_json::json!({ "enabled": false }));
+        assert_eq!(json["series"], serde_json::json!([3.0, 2.0, 1.5]));
+        assert!(json["chart"].get("zoom").is_none());
+        let options = compact(dark.generate_options());
+        assert!(options.contains("theme:{mode:'dark'}"));
+        assert!(options.contains("legend:{show:false}"));
+    }
+
+    #[test]
+    fn heatmap_test() {
+        let values = [[1, 2, 3, 4], [5, 6, 7, 8], [9, 10, 11, 12]];
+        let heatmap = Heatmap::new(&values).with_row_labels(&["a", "b", "c"]);
+        let builder = FigureBuilder::new("", 1, 1, heatmap);
+        let options = compact(builder.clone().generate_options());
+        assert!(options.contains("chart:{type:'heatmap',"));
+        assert_eq!(options.matches("name:").count(), 3);
+        assert_eq!(options.matches("{x:").count(), 12);
+        assert!(options.contains("{name:'b',data:[{x:'0',y:5.0},{x:'1',y:6.0},"));
+        assert!(!options.contains("colorScale"));
+        assert!(options.contains("colors:['#008ffb']"));
+
+        let custom = builder.clone().with_custom_palette(&["#123456"]).generate_options();
+        assert!(compact(custom).contains("colors:['#123456']"));
+
+        let options = compact(builder.clone().with_color_map(ColorMap::Greys).generate_options());
+        assert_eq!(options.matches("color:'#").count(), HEATMAP_RANGES);
+        assert!(options.contains("colorScale:{ranges:[{from:1.0,to:2.375,color:"));
+
+        let dark = builder.with_theme(Theme::Dark).with_legend(LegendPosition::Hidden);
+        let json = serde_json::to_value(dark.chart_options()).unwrap();
+        assert_eq!(json["theme"], serde_json::json!({ "mode": "dark" }));
+        assert_eq!(json["legend"], serde_json::json!({ "show": false }));
+        assert_eq!(json["series"][2]["data"][3], serde_json::json!({ "x": "3", "y": 12.0 }));
+        assert!(json.get("plotOptions").is_none());
+        let options = compact(dark.generate_options());
+        assert!(options.contains("theme:{mode:'dark'}"));
+        assert!(options.contains("legend:{show:false}"));
+    }
+
+    #[test]
+    fn chart_options_test() {
+        let plain = serde_json::to_string(&figure().chart_options()).unwrap();
+        assert_eq!(
+            plain,
+            concat!(
+                r#"{"title":{},"chart":{"type":"line","width":"90%","height":"90%","#,
+                r#""zoom":{"type":"x","enabled":true,"autoScaleYaxis":true},"#,
+                r#""toolbar":{"show":true,"autoSelected":"zoom"}},"#,
+                r#""series":[{"type":"line","data":[[0.0,1.0],[1.0,2.0]]}],"#,
+                r##""fill":{"type":["solid"]},"colors":["#008ffb"],"##,
+                r#""markers":{"shape":["circle"],"size":[4],"fillOpacity":[1],"#,
+                r##""strokeColors":["#ffffff00"],"hover":{"sizeOffset":0},"radius":1},"##,
+                r#""stroke":{"curve":["smooth"],"width":[0],"dashArray":[0],"lineCap":"square"},"#,
+                r#""dataLabels":{"enabled":false},"#,
+                r#""xaxis":{"type":"numeric","tickPlacement":"dataPoints","tooltip":{"enabled":false}}}"#
+            )
+        );
+
+        let a = Series::new(&[0, 1, 2], &[1.0, f64::NAN, 3.0])
+            .with_name("a")
+            .with_style(Style::from("@r^6~~3"))
+            .with_endpoint_marker(true);
+        let b = Series::new(&[0, 1], &[2, 4])
+            .with_yerr(&[0.5, 1.0])
+            .with_style(Style::from("%").with_fill_opacity(0.5))
+            .with_y_axis(1);
+        let builder = FigureBuilder::new("Rich", 800, 600, vec![a, b])
+            .with_subtitle("sub")
+            .with_x_label("x")
+            .with_y_label("y")
+            .with_log_y(true)
+            .with_hline(1.0, "h")
+            .with_vline(2.0, "v")
+            .with_legend(LegendPosition::Top)
+            .with_data_labels(true)
+            .with_data_label_formatter("(v) => v")
+            .with_grid(false)
+            .with_responsive(500)
+            .with_theme(Theme::Dark)
+            .with_stacked(true)
+            .with_horizontal_bars(true)
+            .with_export(true)
+            .with_print_mode();
+
+        let goals = |y: f64, err: f64| {
+            serde_json::json!([
+                { "name": "low", "value": y - err, "strokeColor": "#008ffb", "strokeHeight": 2 },
+                { "name": "high", "value": y + err, "strokeColor": "#008ffb", "strokeHeight": 2 }
+            ])
+        };
+        let font = |size: &str| serde_json::json!({ "style": { "fontSize": size } });
+        let expected = serde_json::json!({
+            "title": { "text": "Rich" },
+            "subtitle": { "text": "sub" },
+            "chart": {
+                "type": "area", "width": 800, "height": 600, "stacked": true,
+                "background": "#ffffff", "animations": { "enabled": false },
+                "zoom": { "type": "x", "enabled": false, "autoScaleYaxis": true },
+                "toolbar": { "show": false, "autoSelected": "zoom", "tools": { "download": true } }
+            },
+            "series": [
+                { "type": "area", "name": "a", "data": [[0.0, 1.0], [1.0, null], [2.0, 3.0]] },
+                { "type": "column", "data": [
+                    { "x": 0.0, "y": 2.0, "goals": goals(2.0, 0.5) },
+                    { "x": 1.0, "y": 4.0, "goals": goals(4.0, 1.0) }
+                ] }
+            ],
+            "fill": { "type": ["solid", "solid"], "opacity": [0.9, 0.5] },
+            "colors": ["#ff0000", "#008ffb"],
+            "markers": {
+                "shape": ["triangle", "circle"], "size": [0, 4], "fillOpacity": [1, 1],
+                "strokeColors": ["#ffffff00", "#ffffff00"], "hover": { "sizeOffset": 0 }, "radius": 1,
+                "discrete": [{
+                    "seriesIndex": 0, "dataPointIndex": 2, "fillColor": "#ff0000",
+                    "strokeColor": "#ff0000", "size": 6
+                }]
+            },
+            "stroke": { "curve": ["smooth", "smooth"], "width": [3, 0], "dashArray": [9, 0], "lineCap": "square" },
+            "dataLabels": { "enabled": true, "formatter": "(v) => v" },
+            "grid": { "show": false },
+            "legend": { "show": true, "position": "top" },
+            "plotOptions": { "bar": { "horizontal": true } },
+            "xaxis": {
+                "type": "numeric", "tickPlacement": "dataPoints",
+                "tooltip": { "enabled": false }, "title": { "text": "x" }
+            },
+            "yaxis": [
+                { "seriesName": "a", "show": true, "opposite": false, "title": { "text": "y" }, "logarithmic": true },
+                { "show": true, "opposite": true, "title": { "text": "y" }, "logarithmic": true }
+            ],
+            "annotations": {
+                "yaxis": [{ "y": 1.0, "label": { "text": "h" } }],
+                "xaxis": [{ "x": 2.0, "label": { "text": "v" } }]
+            },
+            "responsive": [{
+                "breakpoint": 500,
+                "options": {
+                    "legend": { "show": false },
+                    "title": font("12px"),
+                    "xaxis": { "labels": font("10px") },
+                    "yaxis": { "labels": font("10px") }
+                }
+            }]
+        });
+        assert_eq!(serde_json::to_value(builder.chart_options()).unwrap(), expected);
+    }
+
+    #[test]
+    fn raw_injection_test() {
+        let series = Series::new(&[0, 1], &[1, 2]).with_name("\u{2}alert(1)\u{3}");
+        let builder = figure().with_series(series).with_title("\u{2}alert(2)\u{3}").with_data_label_formatter("(v) => v");
+        let json = serde_json::to_string(&builder.chart_options()).unwrap();
+        assert!(!json.contains(['\u{2}', '\u{3}']));
+
+        let js = builder.chart_options().to_js().dump();
+        assert!(!js.contains(['\u{2}', '\u{3}']));
+        assert!(js.contains(r"name: '\u{2}alert(1)\u{3}'"), "{js}");
+        assert!(js.contains(r"text: '\u{2}alert(2)\u{3}'"), "{js}");
+        assert!(js.contains("formatter: (v) => v"));
+    }
+
+    #[test]
+    fn options_json_test() {
+        let series = Series::new(&[-1.0, 2.5e-7], &[f64::NAN, 1.0]).with_name("it's \"quoted\"");
+        let builder = figure()
+            .with_series(series.with_style(Style::from("@r^")))
+            .with_subtitle("a\nb")
+            .with_data_label_formatter("(val) => val + '%'")
+            .with_hline(1.0, "limit")
+            .with_legend(LegendPosition::Left);
+
+        for builder in [builder.clone(), builder.with_minified(true)] {
+            let json: serde_json::Value = serde_json::from_str(&builder.options_json()).unwrap();
+            assert_eq!(json["title"]["text"], serde_json::Value::Null);
+            assert_eq!(json["subtitle"]["text"], "a\nb");
+            assert_eq!(json["series"][1]["name"], "it's \"quoted\"");
+            assert_eq!(json["series"][1]["data"][0], serde_json::json!([-1.0, null]));
+            assert_eq!(json["series"][1]["data"][1][0], 2.5e-7);
+            assert_eq!(json["dataLabels"]["formatter"], "(val) => val + '%'");
+            assert_eq!(json["legend"]["position"], "left");
+        }
+    }
+
+    #[test]
+    fn size_test() {
+        let options = figure().with_size(800, 600).options_json();
+        let json: serde_json::Value = serde_json::from_str(&options).unwrap();
+        assert_eq!((&json["chart"]["width"], &json["chart"]["height"]), (&800.into(), &600.into()));
+
+        let options = figure().options_json();
+        let json: serde_json::Value = serde_json::from_str(&options).unwrap();
+        assert_eq!(json["chart"]["width"], "90%");
+    }
+
+    #[test]
+    fn theme_test() {
+        assert!(!figure().generate_options().contains("theme"));
+        assert!(!figure().build().html.contains(DARK_BACKGROUND));
+
+        let options = compact(figure().with_theme(Theme::Dark).generate_options());
+        assert!(options.contains("theme:{mode:'dark'}"));
+        assert!(figure().with_theme(Theme::Dark).build().html.contains("background: #1e1e1e;"));
+
+        let print = figure().with_theme(Theme::Dark).with_print_mode();
+        assert!(!print.clone().generate_options().contains("theme"));
+        assert!(print.build().html.contains("background: #ffffff;"));
+    }
+
+    #[test]
+    fn named_y_axes_test() {
+        let options = compact(
+            FigureBuilder::default()
+                .add_y_axis("temp", AxisConfig::new("°C", false))
+                .add_y_axis("pressure", AxisConfig::new("hPa", true))
+                .with_series(Series::new(&[0], &[20]).with_name("a"))
+                .with_series(Series::new(&[0], &[1013]).with_name("b").with_y_axis_name("pressure"))
+                .with_series(Series::new(&[0], &[22]).with_name("c").with_y_axis_name("temp"))
+                .generate_options(),
+        );
+        assert!(options.contains(
+            "yaxis:[\
+            {seriesName:'a',show:true,opposite:false,title:{text:'°C'}},\
+            {seriesName:'b',show:true,opposite:true,title:{text:'hPa'}},\
+            {seriesName:'a',show:false,opposite:false,title:{text:'°C'}}]"
+        ));
+        assert!(!compact(figure().generate_options()).contains("yaxis"));
+    }
+
+    #[test]
+    fn indexed_y_axes_test() {
+        let options = compact(
+            FigureBuilder::default()
+                .with_series(Series::new(&[0], &[20]).with_name("temp"))
+                .with_series(Series::new(&[0], &[1013]).with_name("pressure").with_y_axis(1))
+                .with_series(Series::new(&[0], &[22]).with_name("other").with_y_axis(0))
+                .generate_options(),
+        );
+        assert!(options.contains(
+            "yaxis:[\
+            {seriesName:'temp',show:true,opposite:false},\
+            {seriesName:'pressure',show:true,opposite:true},\
+            {seriesName:'temp',show:false,opposite:false}]"
+        ));
+
+        let options = compact(
+            FigureBuilder::default()
+                .add_y_axis("temp", AxisConfig::new("°C", false))
+                .with_series(Series::new(&[0], &[20]).with_name("a"))
+                .with_series(Series::new(&[0], &[22]).with_name("b").with_y_axis(0))
+                .generate_options(),
+        );
+        assert!(options.contains(
+            "yaxis:[\
+            {seriesName:'a',show:true,opposite:false,title:{text:'°C'}},\
+            {seriesName:'a',show:false,opposite:false,title:{text:'°C'}}]"
+        ));
+    }
+
+    #[test]
+    fn log_y_test() {
+        assert!(!figure().generate_options().contains("logarithmic"));
+
+        let options = compact(figure().with_log_y(true).generate_options());
+        assert!(options.contains("yaxis:{show:true,opposite:false,logarithmic:true}"));
+
+        let options = compact(
+            figure()
+                .with_log_y(true)
+                .with_log_base(2.0)
+                .with_series(Series::new(&[0], &[1]).with_y_axis(1))
+                .generate_options(),
+        );
+        assert!(options.contains(
+            "yaxis:[\
+            {show:true,opposite:false,logarithmic:true,logBase:2.0},\
+            {show:true,opposite:true,logarithmic:true,logBase:2.0}]"
+        ));
+    }
+
+    #[test]
+    fn y_range_test() {
+        let options = compact(figure().generate_options());
+        assert!(!options.contains("yaxis"));
+
+        let options = compact(figure().with_y_range(-1.5, 10.0).generate_options());
+        assert!(options.contains("yaxis:{show:true,opposite:false,min:-1.5,max:10.0}"));
+    }
+
+    #[test]
+    fn robust_y_test() {
+        let mut y: Vec<f64> = (0..100).map(f64::from).collect();
+        y.push(1e6);
+        let x: Vec<f64> = (0..y.len()).map(|i| i as f64).collect();
+        let fig = figure().with_series(Series::new(&x, &y)).with_robust_y(true);
+        let json: serde_json::Value = serde_json::from_str(&fig.options_json()).unwrap();
+        let max = json["yaxis"]["max"].as_f64().unwrap();
+        assert!(max < 1e6);
+        assert_eq!(json["yaxis"]["min"], 1.0);
+
+        let fixed = fig.with_y_range(-1.0, 5.0).options_json();
+        let json: serde_json::Value = serde_json::from_str(&fixed).unwrap();
+        assert_eq!((json["yaxis"]["min"].as_f64(), json["yaxis"]["max"].as_f64()), (Some(-1.0), Some(5.0)));
+    }
+
+    #[test]
+    fn axis_labels_test() {
+        let plain = compact(figure().generate_options());
+        let unlabeled = compact(figure().with_x_label("").with_y_label("").generate_options());
+        assert_eq!(plain, unlabeled);
+
+        let options = compact(
+            figure()
+                .with_x_label("time [s]")
+                .with_y_label("speed (m/s)")
+                .generate_options(),
+        );
+        assert!(options.contains("tooltip:{enabled:false},title:{text:'time[s]'}}"));
+        assert!(options.contains("yaxis:{show:true,opposite:false,title:{text:'speed(m/s)'}}"));
+    }
+
+    #[test]
+    fn summary_json_test() {
+        let summary = FigureBuilder::default()
+            .with_title("say \"hi\"")
+            .with_series(Series::new(&[0, 1, 2], &[1, 2, 3]).with_name("a"))
+            .with_series(Series::new(&[0], &[1]).with_style(Style::default().with_typ("area")))
+            .summary_json();
+        assert_eq!(
+            summary,
+            "{\"title\":\"say \\\"hi\\\"\",\"series_count\":2,\"series\":[\
+            {\"name\":\"a\",\"type\":\"line\",\"points\":3},\
+            {\"name\":null,\"type\":\"area\",\"points\":1}]}"
+        );
+    }
+
+    #[test]
+    fn endpoint_marker_test() {
+        let options = compact(
+            figure()
+                .with_series(Series::new(&[0, 1, 2], &[3, 1, 2]).with_endpoint_marker(true))
+                .generate_options(),
+        );
+        assert!(options.contains("size:[4,0]"));
+        assert!(options.contains(
+            "discrete:[{seriesIndex:1,dataPointIndex:2,fillColor:'#00e396',strokeColor:'#00e396',size:4}]"
+        ));
+        assert!(!compact(figure().generate_options()).contains("discrete"));
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn from_csv_test() {
+        use std::{env, fs};
+        let path = env::temp_dir().join("axoplotl-from-csv-test.csv");
+        fs::write(&path, "t, a, b\n0, 1.5, 10\n1, 2.5, 20\n2, 3.5, 30\n").unwrap();
+
+        let fig = FigureBuilder::from_csv(&path, "t", &["a", "b"]).unwrap();
+        assert_eq!(
+            fig.data,
+            vec![
+                Series::new(&[0, 1, 2], &[1.5, 2.5, 3.5]).with_name("a"),
+                Series::new(&[0, 1, 2], &[10, 20, 30]).with_name("b"),
+            ]
+        );
+
+        let err = FigureBuilder::from_csv(&path, "t", &["c"]).unwrap_err();
+        assert!(err.to_string().contains("column 'c' not found"));
+
+        fs::write(&path, "t,a\n0,1\n1,x\n").unwrap();
+        let err = FigureBuilder::from_csv(&path, "t", &["a"]).unwrap_err();
+        assert!(err.to_string().contains("cannot parse 'x' in column 'a'"));
+
+        fs::write(&path, "0,1\n1,2\n").unwrap();
+        let err = FigureBuilder::from_csv(&path, "t", &["a"]).unwrap_err();
+        assert!(err.to_string().contains("does not start with a header row"));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn image_format_test() {
+        let image = RgbImage::new(4, 4);
+        let png = FigureBuilder::new("img", 4, 4, &image).build();
+        assert!(png.html.contains("src='data:image/png;base64,"));
+
+        let jpeg = FigureBuilder::new("img", 4, 4, &image)
+            .with_format(WebFormat::Jpeg(90))
+            .build();
+        assert!(jpeg.html.contains("src='data:image/jpeg;base64,/9j/"));
+    }
+
+    #[cfg(not(feature = "webp"))]
+    #[test]
+    fn webp_fallback_test() {
+        let image = RgbImage::new(4, 4);
+        let webp = FigureBuilder::new("img", 4, 4, &image).with_format(WebFormat::WebP(80)).build();
+        assert_eq!(webp, FigureBuilder::new("img", 4, 4, &image).build());
+    }
+
+    #[test]
+    fn image_background_test() {
+        let image = image::RgbaImage::new(2, 2);
+        let plain = FigureBuilder::new("img", 2, 2, &image).build();
+        assert!(!plain.html.contains("background"));
+        let fig = FigureBuilder::new("img", 2, 2, &image).with_background("#fafafa").build();
+        assert!(fig.html.contains("justify-content: center; background: #fafafa;}"));
+    }
+
+    #[test]
+    fn imshow_color_map_test() {
+        let image = image::GrayImage::from_fn(4, 1, |x, _| image::Luma([x as u8 * 60]));
+        let fig = imshow!(image, "magma");
+        assert_eq!(fig.image, Some(image.colormap(ColorMap::Magma).encode64()));
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown colormap 'magam'")]
+    fn imshow_unknown_color_map_test() {
+        let image = image::GrayImage::new(1, 1);
+        imshow!(image, "magam");
+    }
+
+    #[test]
+    fn image_max_dim_test() {
+        let image = RgbImage::new(100, 50);
+        let fig = FigureBuilder::new("img", 100, 50, &image).with_max_dim(10).build();
+        assert!(fig.html.contains(&image.encode64_max_dim(10)));
+        assert_eq!(imshow!(image, max_dim = 10).html, fig.html);
+
+        let tiny = RgbImage::new(2, 2);
+        let upscaled = imshow!(tiny, upscale = 3);
+        assert_eq!(upscaled.name, "tiny");
+        assert_eq!(upscaled.image, Some(RgbImage::new(6, 6).encode64()));
+
+        let scaled = FigureBuilder::new("img", 100, 50, &image)
+            .with_max_dim(10)
+            .with_scale_bar(1.0, "px")
+            .build();
+        assert!(!scaled.html.contains(&image.encode64_max_dim(10)));
+    }
+
+    #[test]
+    #[should_panic(expected = "pixels per unit must be finite and positive, got 0")]
+    fn scale_bar_zero_test() {
+        FigureBuilder::new("img", 2, 2, RgbImage::new(2, 2)).with_scale_bar(0.0, "µm");
+    }
+
+    fn element_id(html: &str) -> String {
+        let start = html.find(" id='").unwrap() + 5;
+        html[start..].split('\'').next().unwrap().to_string()
+    }
+
+    #[test]
+    fn unique_id_test() {
+        let fig = || figure().with_title("Revenue (USD)").build();
+        assert_eq!(fig(), fig());
+        let (a, b) = (fig(), figure().with_title("Costs").build());
+        assert_ne!(element_id(&a.html), element_id(&b.html));
+        assert!(element_id(&a.html).starts_with("chart-"));
+        for fig in [&a, &b] {
+            let id = element_id(&fig.html);
+            assert_eq!(fig.html.matches(&format!("#{id} {{")).count(), 1);
+            assert_eq!(fig.html.matches(&format!("querySelector('#{id}')")).count(), 1);
+            assert!(!fig.html.contains(ID_PLACEHOLDER));
+        }
+
+        let image = || FigureBuilder::new("µ/m", 1, 1, RgbImage::new(1, 1)).build();
+        assert_eq!(image(), image());
+        assert!(image().html.contains(&format!("<img id='{}'", element_id(&image().html))));
+        let untitled = FigureBuilder::<Vec<Series>>::new("", 1, 1, vec![]).build();
+        assert!(untitled.html.contains("<div id='chart-"));
+    }
+
+    #[test]
+    fn grid_test() {
+        let a = figure().build();
+        let id = element_id(&a.html);
+        assert!(a.html.contains(&format!("querySelector('#{id}')")));
+
+        let build = || Grid::new(2, 2).with_figure(a.clone()).with_figure(a.clone()).with_figure(image_fig()).build();
+        let grid = build();
+        assert_eq!(grid, build());
+        assert!(grid.html.contains("grid-template-columns: repeat(2, 1fr)"));
+        assert_eq!(grid.html.matches("<div class='axoplotl-cell'>").count(), 3);
+        assert_eq!(grid.name, "grid");
+
+        // One runtime for the whole page, ahead of the cells
+        assert_eq!(grid.html.matches(CDN_SCRIPT).count(), 1);
+        assert!(grid.html.starts_with(CDN_SCRIPT));
+        for k in 0..2 {
+            assert!(grid.html.contains(&format!("<div id='{id}-cell{k}'>")));
+            assert!(grid.html.contains(&format!("querySelector('#{id}-cell{k}')")));
+        }
+        assert!(!grid.html.contains(&format!("'{id}'")));
+
+        let images = Grid::new(1, 1).with_figure(image_fig()).build();
+        assert!(!images.html.contains("<script"));
+    }
+
+    fn image_fig() -> Figure {
+        FigureBuilder::new("img", 1, 1, RgbImage::new(1, 1)).build()
+    }
+
+    #[test]
+    fn composite_test() {
+        let image = FigureBuilder::new("img", 2, 2, RgbImage::new(2, 2));
+        let builder = Composite::new(image, figure());
+        let composite = builder.clone().build();
+        assert_eq!(composite.name(), "composite");
+        assert!(composite.html().contains("<img id='chart-"));
+        assert!(composite.html().contains("-cell0'"));
+        assert!(composite.html().contains("new ApexCharts(document.querySelector('#chart-"));
+        assert!(composite.html().starts_with(CDN_SCRIPT));
+        assert!(composite.html().contains("grid-template-columns: repeat(2, 1fr)"));
+
+        let titled = builder.with_title("rows").build();
+        assert_eq!(titled.name(), "rows");
+        assert_eq!(titled.html().matches("<img ").count(), 1);
+        assert_eq!(titled.html().matches("<script").count(), 2);
+        let tags = titled.html().split(" id='").skip(1);
+        let ids: Vec<&str> = tags.map(|tag| &tag[..tag.find('\'').unwrap()]).collect();
+        assert_eq!(ids.len(), 2);
+        assert_ne!(ids[0], ids[1]);
+        assert!(ids.iter().all(|id| id.starts_with("chart-")));
+    }
+
+    #[test]
+    fn html_test() {
+        let chart = figure().with_title("chart").build();
+        assert_eq!(chart.name(), "chart");
+        assert!(chart.html().contains("<script>"));
+
+        let image = FigureBuilder::new("img", 1, 1, RgbImage::new(1, 1)).build();
+        assert_eq!(image.name(), "img");
+        assert!(image.html().contains("<img id='chart-"));
+    }
+
+    // Runs with `--no-default-features` too, where the output module is not compiled
+    #[test]
+    fn options_without_output_test() {
+        let builder = figure().with_title("pure");
+        let json: serde_json::Value = serde_json::from_str(&builder.options_json()).unwrap();
+        assert_eq!(json["series"][0]["data"], serde_json::json!([[0.0, 1.0], [1.0, 2.0]]));
+
+        let fig = builder.build();
+        assert_eq!(fig.name(), "pure");
+        assert!(fig.html().contains("new ApexCharts"));
+    }
+
+    #[test]
+    #[cfg(not(feature = "io"))]
+    fn options_without_io_test() {
+        let options = compact(figure().with_title("pure").generate_options());
+        assert!(options.starts_with("{title:{text:'pure'}"));
+        assert!(figure().build().html().contains("chart.render();"));
+    }
+
+    #[test]
+    fn vega_lite_test() {
+        let fig = figure()
+            .with_title("vega")
+            .with_series(Series::new(&[0, 1], &[3, 4]).with_name("b").with_style(Style::from("r~")));
+        let spec: serde_json::Value = serde_json::from_str(&fig.to_vega_lite()).unwrap();
+        let layers = spec["layer"].as_array().unwrap();
+        assert_eq!(layers.len(), 2);
+        assert_eq!(layers[0]["mark"]["type"], "point");
+        assert_eq!(layers[1]["mark"]["type"], "line");
+        assert_eq!(layers[1]["transform"], serde_json::json!([{ "filter": { "field": "series", "equal": "b" } }]));
+        assert_eq!(spec.get("mark"), None);
+        assert_eq!(spec["title"], "vega");
+        assert_eq!(spec["data"]["values"].as_array().unwrap().len(), 4);
+        assert_eq!(spec["data"]["values"][2], serde_json::json!({"x": 0.0, "y": 3.0, "series": "b"}));
+        assert_eq!(spec["encoding"]["color"]["scale"]["domain"], serde_json::json!(["series_1", "b"]));
+        assert_eq!(spec["encoding"]["color"]["scale"]["range"][1], "#ff0000");
+
+        let mixed = FigureBuilder::<Vec<Series>>::default()
+            .with_series(Series::new(&[0], &[1]).with_style(Style::from("%")))
+            .with_series(Series::new(&[0], &[2]).with_style(Style::from("@")))
+            .with_series(Series::new(&[0], &[3]).with_style(Style::points_only(4)));
+        let spec: serde_json::Value = serde_json::from_str(&mixed.to_vega_lite()).unwrap();
+        let layers = spec["layer"].as_array().unwrap();
+        let marks: Vec<_> = layers.iter().map(|layer| layer["mark"]["type"].as_str().unwrap()).collect();
+        assert_eq!(marks, ["bar", "area", "point"]);
+        assert_eq!(spec.get("title"), None);
+    }
+
+    #[test]
+    fn to_csv_test() {
+        let fig = FigureBuilder::<Vec<Series>>::default()
+            .with_series(Series::new(&[0, 1, 2], &[1.5, 2.5, f64::NAN]).with_name("a"))
+            .with_series(Series::new(&[2, 1], &[20, 10]).with_name("b, c"));
+        assert_eq!(fig.to_csv(), "x,a,\"b, c\"\n0,1.5,\n1,2.5,10\n2,,20\n");
+
+        let repeated = FigureBuilder::<Vec<Series>>::default()
+            .with_series(Series::new(&[0, 0], &[1, 2]))
+            .with_series(Series::new(&[1], &[3]).with_name("b"));
+        assert_eq!(repeated.to_csv(), "series,x,y\nseries_1,0,1\nseries_1,0,2\nb,1,3\n");
+    }
+
+    #[test]
+    fn svg_test() {
+        let line = Series::new(&[0, 1], &[1, 2]).with_style(Style::from("r.3/2"));
+        let svg = FigureBuilder::<Vec<Series>>::default()
+            .with_title("line")
+            .with_size(400, 300)
+            .with_series(line)
+            .build_svg();
+        assert_eq!(svg, include_str!("../tests/golden/line.svg"));
+    }
+
+    #[test]
+    fn image_encode_error_test() {
+        let empty = RgbImage::new(0, 0);
+        assert!(FigureBuilder::new("img", 0, 0, &empty).try_build().is_err());
+        let webp = FigureBuilder::new("img", 0, 0, &empty).with_format(WebFormat::WebP(80));
+        assert!(webp.try_build().is_err());
+    }
+
+    #[test]
+    fn error_bars_test() {
+        let plain = compact(figure().generate_options());
+        assert!(plain.contains("data:[[0.0,1.0],[1.0,2.0]]"));
+        assert!(!plain.contains("goals"));
+
+        let errors = Series::new(&[0, 1], &[1, 2]).with_yerr(&[0.5, 0.25]);
+        let line = FigureBuilder::default().with_series(errors.clone()).chart_options();
+        assert_eq!(line.series[0].typ, "line");
+        assert!(matches!(line.series[0].data, SeriesData::Points(_)));
+
+        let columns = errors.with_style(Style::from("%"));
+        let builder = FigureBuilder::default().with_series(columns);
+        let chart = builder.chart_options();
+        assert_eq!(chart.series[0].typ, "column");
+        assert!(matches!(chart.series[0].data, SeriesData::Goals(_)));
+        let options = compact(builder.generate_options());
+        assert!(options.contains(
+            "data:[{x:0.0,y:1.0,goals:[\
+            {name:'low',value:0.5,strokeColor:'#008ffb',strokeHeight:2},\
+            {name:'high',value:1.5,strokeColor:'#008ffb',strokeHeight:2}]},\
+            {x:1.0,y:2.0,goals:[\
+            {name:'low',value:1.75,strokeColor:'#008ffb',strokeHeight:2},\
+            {name:'high',value:2.25,strokeColor:'#008ffb',strokeHeight:2}]}]"
+        ));
+    }
+
+    #[test]
+    fn points_only_test() {
+        let points = Series::new(&[0, 1], &[2, 3]).with_style(Style::points_only(8));
+        let options = FigureBuilder::<Vec<Series>>::default().with_series(points).chart_options();
+        assert_eq!(options.stroke.width, vec![0]);
+        assert_eq!(options.markers.size, vec![8]);
+        assert_eq!(options.markers.fill_opacity, vec![1]);
+    }
+
+    #[test]
+    fn point_colors_test() {
+        let scatter = Series::new(&[0, 1], &[2, 3]).with_point_colors(&["#ff0000", "blue"]);
+        let line = scatter.clone().with_style(Style::from("/2"));
+        let fig = FigureBuilder::<Vec<Series>>::default().with_series(scatter).with_series(line);
+        let json: serde_json::Value = serde_json::from_str(&fig.options_json()).unwrap();
+        assert_eq!(
+            json["series"][0]["data"],
+            serde_json::json!([
+                {"x": 0.0, "y": 2.0, "fillColor": "#ff0000"},
+                {"x": 1.0, "y": 3.0, "fillColor": "blue"},
+            ])
+        );
+        assert_eq!(json["series"][1]["data"], serde_json::json!([[0.0, 2.0], [1.0, 3.0]]));
+        assert!(fig.build_svg().contains("fill=\"blue\""));
+    }
+
+    #[test]
+    fn horizontal_bars_test() {
+        assert!(!figure().generate_options().contains("plotOptions"));
+        assert!(!figure().with_horizontal_bars(false).generate_options().contains("plotOptions"));
+
+        let options = compact(figure().with_horizontal_bars(true).generate_options());
+        assert!(options.contains("plotOptions:{bar:{horizontal:true}}"));
+    }
+
+    #[test]
+    fn annotations_test() {
+        assert!(!figure().generate_options().contains("annotations"));
+
+        let options = compact(
+            figure()
+                .with_hline(1.5, "threshold")
+                .with_hline(-2.0, "floor")
+                .with_vline(10.0, "release")
+                .generate_options(),
+        );
+        assert!(options.contains(
+            "annotations:{\
+            yaxis:[{y:1.5,label:{text:'threshold'}},{y:-2.0,label:{text:'floor'}}],\
+            xaxis:[{x:10.0,label:{text:'release'}}]}"
+        ));
+    }
+
+    #[test]
+    fn chart_type_test() {
+        let with = |styles: &[&str]| {
+            let series = styles.iter().map(|&s| Series::new(&[0], &[1]).with_style(s.into()));
+            compact(FigureBuilder::new("", 1, 1, series.collect::<Vec<_>>()).generate_options())
+        };
+        assert!(with(&["", "~"]).contains("chart:{type:'line'"));
+        assert!(with(&["", "@"]).contains("chart:{type:'area'"));
+        assert!(with(&["%", "%"]).contains("chart:{type:'bar'"));
+        assert!(with(&["%", ""]).contains("chart:{type:'line'"));
+        assert!(with(&["%", "@", ""]).contains("chart:{type:'area'"));
+    }
+
+    #[test]
+    fn stacked_test() {
+        let area = || figure().with_series(Series::new(&[0], &[1]).with_style(Style::from("@")));
+        let options = compact(area().generate_options());
+        assert!(!options.contains("stacked"));
+        assert!(options.contains("fill:{type:['solid','gradient']}"));
+
+        let options = compact(area().with_stacked(true).generate_options());
+        assert!(options.contains("height:'90%',stacked:true,"));
+        assert!(options.contains("fill:{type:['solid','solid']}"));
+    }
+
+    #[test]
+    fn fill_opacity_test() {
+        let options = compact(figure().generate_options());
+        assert!(options.contains("fill:{type:['solid']}"));
+
+        let series = |opacity| {
+            let style = Style::from("@").with_fill_opacity(opacity);
+            Series::new(&[0], &[1]).with_style(style)
+        };
+        let series = vec![series(0.25), series(0.5)];
+        let options = compact(FigureBuilder::new("", 1, 1, series).generate_options());
+        assert!(options.contains("fill:{type:['gradient','gradient'],opacity:[0.25,0.5]}"));
+    }
+
+    #[test]
+    fn stroke_opacity_test() {
+        let options = compact(figure().generate_options());
+        assert!(!options.contains("opacity"));
+
+        let series = Series::new(&[0], &[1]).with_style(Style::from("#ff000080"));
+        let options = compact(figure().with_series(series).generate_options());
+        assert!(options.contains("colors:['#008ffb','#ff0000']"));
+        assert!(options.contains("dashArray:[0,0],colors:['#008ffb','rgba(255,0,0,0.502)']"));
+        assert!(!options.contains("opacity"));
+
+        let series = Series::new(&[0], &[1]).with_style(Style::from("#ff000080").with_stroke_opacity(0.25));
+        let json = serde_json::to_value(FigureBuilder::new("", 1, 1, vec![series]).chart_options()).unwrap();
+        assert_eq!(json["stroke"]["colors"], serde_json::json!(["rgba(255, 0, 0, 0.25)"]));
+        assert_eq!(json["colors"], serde_json::json!(["#ff0000"]));
+    }
+
+    #[test]
+    fn dash_array_test() {
+        let stroke = Stroke::new("straight", 2, true).with_dash_array(7);
+        let series = Series::new(&[0], &[1]).with_style(Style::from("r~~").with_stroke(stroke));
+        let options = compact(figure().with_series(series).generate_options());
+        assert!(options.contains("dashArray:[0,7]"));
+    }
+
+    #[test]
+    fn custom_palette_test() {
+        let builder = figure()
+            .with_custom_palette(&["#111111", "#222222"])
+            .with_series(Series::new(&[0, 1], &[2, 3]))
+            .with_series(Series::new(&[0, 1], &[3, 4]));
+        assert_eq!(builder.palette_colors(), ["#111111", "#222222"]);
+        assert_eq!(builder.palette(), COLOR_PALLETS[0]);
+        let options = compact(builder.clone().generate_options());
+        assert!(options.contains("colors:['#111111','#222222','#111111']"));
+
+        let options = compact(builder.with_palette(1).generate_options());
+        assert!(options.contains(&format!("colors:['{}',", COLOR_PALLETS[1][0])));
+        assert_eq!(figure().with_custom_palette(&[]).palette_colors(), COLOR_PALLETS[0]);
+    }
+
+    #[test]
+    fn auto_colors_test() {
+        let builder = (0..8).fold(FigureBuilder::<Vec<Series>>::default().with_palette(9), |fig, i| {
+            fig.with_series(Series::new(&[0, 1], &[i, i + 1]))
+        });
+        let colors = builder.chart_options().colors;
+        assert_eq!(colors[..5], COLOR_PALLETS[9]);
+        assert_eq!(colors[5], COLOR_PALLETS[0][0]);
+        for (i, color) in colors.iter().enumerate() {
+            assert!(!colors[..i].contains(color));
+        }
+    }
+
+    #[test]
+    fn series_colors_test() {
+        let fig = FigureBuilder::<Vec<Series>>::default()
+            .with_series(Series::new(&[0], &[1]).with_style(Style::from("#ff000080")))
+            .with_series(Series::new(&[0], &[1]).with_style(Style::from("p2")))
+            .with_series(Series::new(&[0], &[1]));
+        let palette = fig.palette();
+        assert_eq!(fig.series_colors(), vec!["#ff0000", palette[2], palette[0]]);
+    }
+
+    #[test]
+    fn palette_color_test() {
+        let series = Series::new(&[0], &[1]).with_style(Style::from("p3"));
+        let options = compact(figure().with_palette(2).with_series(series).generate_options());
+        let palette = COLOR_PALLETS[2];
+        assert!(options.contains(&format!("colors:['{}','{}']", palette[0], palette[3])));
+    }
+
+    #[test]
+    fn non_finite_test() {
+        let series = Series::new(&[0.0, 1.0, 2.0], &[1.0, f64::NAN, f64::INFINITY]);
+        let options = compact(figure().with_series(series.clone()).generate_options());
+        assert!(options.contains("data:[[0.0,1.0],[1.0,null],[2.0,null]]"));
+
+        let series = series.with_non_finite(NonFinite::Drop).with_yerr(&[1, 1, 1]);
+        let series = series.with_style(Style::from("%"));
+        let options = compact(figure().with_series(series).generate_options());
+        assert!(options.contains("data:[{x:0.0,y:1.0,goals:"));
+        assert!(!options.contains("x:1.0"));
+    }
+
+    #[test]
+    fn plot_macro_test() {
+        let (a, b) = ([1, 2, 3], [4, 5]);
+        let x: Vec<f64> = (1..4).map(|v| v as f64 * 10.0).collect();
+        let fig = plot!((x.iter().map(|v| v / 10.0).collect::<Vec<_>>(), a, "r"); b);
+        let html = compact(fig.html);
+        assert!(html.contains("name:'a',data:[[1.0,1.0],[2.0,2.0],[3.0,3.0]]"));
+        assert!(html.contains("name:'b',data:[[0.0,4.0],[1.0,5.0]]"));
+        assert!(html.contains("colors:['#ff0000','#008ffb']"));
+
+        let style = Style::default().with_color("black");
+        let html = compact(plot!(x, a; (b, style = style)).html);
+        assert!(html.contains("colors:['#008ffb','#000000']"));
+        assert!(html.contains("name:'a',data:[[10.0,1.0],[20.0,2.0],[30.0,3.0]]"));
+    }
+
+    #[test]
+    fn gridlines_test() {
+        let options = compact(figure().generate_options());
+        assert!(!options.contains("grid:"));
+
+        let options = compact(figure().with_grid(false).generate_options());
+        assert!(options.contains("grid:{show:false},"));
+
+        let options = compact(figure().with_grid_color("#eee").generate_options());
+        assert!(options.contains("grid:{borderColor:'#eee'},"));
+    }
+
+    #[test]
+    fn data_labels_test() {
+        let options = compact(figure().generate_options());
+        assert!(options.contains("dataLabels:{enabled:false},"));
+
+        let options = compact(figure().with_data_labels(true).generate_options());
+        assert!(options.contains("dataLabels:{enabled:true},"));
+
+        let formatter = "(val) => val.toFixed(1) + \"%\"";
+        let options = figure().with_data_label_formatter(formatter).generate_options();
+        assert!(options.contains("formatter: (val) => val.toFixed(1) + \"%\""));
+    }
+
+    #[test]
+    fn legend_test() {
+        assert!(!figure().generate_options().contains("legend"));
+
+        for (legend, position) in [
+            (LegendPosition::Top, "top"),
+            (LegendPosition::Bottom, "bottom"),
+            (LegendPosition::Left, "left"),
+            (LegendPosition::Right, "right"),
+        ] {
+            let options = compact(figure().with_legend(legend).generate_options());
+            assert!(options.contains(&format!("legend:{{show:true,position:'{position}'}},")));
+        }
+        let options = compact(figure().with_legend(LegendPosition::Hidden).generate_options());
+        assert!(options.contains("legend:{show:false},"));
+    }
+
+    #[test]
+    fn subtitle_test() {
+        assert!(!figure().generate_options().contains("subtitle"));
+
+        let options = compact(figure().with_subtitle("Last week").generate_options());
+        assert!(options.contains("subtitle:{text:'Lastweek'},"));
+    }
+
+    #[test]
+    fn datetime_test() {
+        let options = compact(figure().generate_options());
+        assert!(options.contains("xaxis:{type:'numeric'"));
+        assert!(!options.contains("tooltip:{x:"));
+
+        let options = compact(
+            figure()
+                .with_series(Series::new_timeseries(&[1_700_000_000_000], &[1]))
+                .generate_options(),
+        );
+        assert!(options.contains("xaxis:{type:'datetime'"));
+        assert!(options.contains("tooltip:{x:{format:'ddMMMyyyyHH:mm:ss'}}"));
+    }
+
+    #[test]
+    fn minified_test() {
+        let pretty = figure().with_title("a b").generate_options();
+        let minified = figure().with_title("a b").with_minified(true).generate_options();
+        assert!(pretty.contains('\n'));
+        assert!(!minified.contains('\n'));
+        assert!(minified.starts_with("{title:{text:'a b'},chart:{"));
+        assert_eq!(compact(minified), compact(pretty));
+    }
+
+    #[test]
+    fn responsive_test() {
+        assert!(!figure().generate_options().contains("responsive"));
+
+        let options = figure().with_responsive(600).generate_options();
+        assert!(options.contains("responsive: ["));
+        assert!(options.contains("breakpoint: 600"));
+    }
 }
@@ -0,0 +1,87 @@
+use crate::webimg::ColorMap;
+
+/// Matrix of values plotted as a grid of colored cells, one chart row per matrix row
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Heatmap {
+    values: Vec<Vec<f64>>,
+    row_labels: Option<Vec<String>>,
+    col_labels: Option<Vec<String>>,
+    pub color_map: Option<ColorMap>,
+}
+
+impl Heatmap {
+    pub fn new<R, T>(rows: &[R]) -> Self
+    where
+        R: AsRef<[T]>,
+        T: Into<f64> + Copy,
+    {
+        let values: Vec<Vec<f64>> = rows
+            .iter()
+            .map(|row| row.as_ref().iter().map(|&v| v.into()).collect())
+            .collect();
+        if let Some(first) = values.first() {
+            let same = values.iter().all(|row| row.len() == first.len());
+            assert!(same, "heatmap rows have different lengths");
+        }
+        Heatmap { values, ..Default::default() }
+    }
+
+    pub fn values(&self) -> &[Vec<f64>] {
+        &self.values
+    }
+
+    pub fn rows(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn cols(&self) -> usize {
+        self.values.first().map_or(0, Vec::len)
+    }
+
+    /// Label of each row, rows are numbered from 0 by default
+    pub fn row_label(&self, row: usize) -> String {
+        match &self.row_labels {
+            Some(labels) => labels[row].clone(),
+            None => row.to_string(),
+        }
+    }
+
+    /// Label of each column, columns are numbered from 0 by default
+    pub fn col_label(&self, col: usize) -> String {
+        match &self.col_labels {
+            Some(labels) => labels[col].clone(),
+            None => col.to_string(),
+        }
+    }
+
+    pub fn with_row_labels<S: Into<String> + Clone>(mut self, labels: &[S]) -> Self {
+        assert_eq!(labels.len(), self.rows(), "row labels and rows have different lengths");
+        self.row_labels = Some(labels.iter().cloned().map(Into::into).collect());
+        self
+    }
+
+    pub fn with_col_labels<S: Into<String> + Clone>(mut self, labels: &[S]) -> Self {
+        assert_eq!(labels.len(), self.cols(), "column labels and columns have different lengths");
+        self.col_labels = Some(labels.iter().cloned().map(Into::into).collect());
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels_test() {
+        let heatmap = Heatmap::new(&[[1, 2, 3], [4, 5, 6]]).with_col_labels(&["a", "b", "c"]);
+        assert_eq!((heatmap.rows(), heatmap.cols()), (2, 3));
+        assert_eq!((heatmap.row_label(1), heatmap.col_label(1)), ("1".into(), "b".into()));
+        assert_eq!(heatmap.values()[1], vec![4.0, 5.0, 6.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "heatmap rows have different lengths")]
+    fn ragged_test() {
+        Heatmap::new(&[vec![1, 2], vec![3]]);
+    }
+}
@@ -1,5 +1,9 @@
 use std::fmt::{Debug, Display};
 
+// Raw js is wrapped in control characters, so that nested `js!` calls leave it untouched
+const RAW_START: char = '\u{2}';
+const RAW_END: char = '\u{3}';
+
 pub struct JS(String);
 
 impl Display for JS {
@@ -27,28 +31,231 @@ impl From<String> for JS {
 }
 
 impl JS {
-    fn transform(mut s: String) -> String {
-        let (from, to) = ([b'\"', b'(', b')'], [b'\'', b'[', b']']);
-
-        let bytes = unsafe { s.as_bytes_mut() };
-        for b in bytes {
-            if let Some(idx) = from.iter().position(|c| c == b) {
-                *b = to[idx] as u8;
+    // Converts rust syntax into js, string literals keep their contents and are requoted with
+    // single quotes, escaping any single quotes inside
+    fn transform(s: String) -> String {
+        let mut js = String::with_capacity(s.len());
+        let (mut chars, mut quote) = (s.chars(), None);
+        while let Some(c) = chars.next() {
+            match (quote, c) {
+                (Some(_), '\\') => {
+                    js.push(c);
+                    js.extend(chars.next());
+                }
+                (Some(q), c) if c == q => {
+                    quote = None;
+                    js.push('\'');
+                }
+                (Some('"'), '\'') => js.push_str("\\'"),
+                (Some(_), c) => js.push(c),
+                (None, '"' | '\'') => {
+                    quote = Some(c);
+                    js.push('\'');
+                }
+                (None, RAW_START) => {
+                    js.push(c);
+                    for c in chars.by_ref() {
+                        js.push(c);
+                        if c == RAW_END {
+                            break;
+                        }
+                    }
+                }
+                (None, '(') => js.push('['),
+                (None, ')') => js.push(']'),
+                (None, c) => js.push(c),
             }
         }
-        s
+        js
     }
 
-    fn peek_next(bytes: &[u8]) -> Option<u8> {
-        if let Some(idx) = bytes.iter().position(|b| !b.is_ascii_whitespace()) {
-            Some(bytes[idx])
-        } else {
-            None
+    // Index right after the string literal or raw js starting at `at`
+    fn literal_end(bytes: &[u8], at: usize) -> usize {
+        let end = match bytes[at] {
+            b'\x02' => b'\x03',
+            quote => quote,
+        };
+        let mut i = at + 1;
+        while i < bytes.len() && bytes[i] != end {
+            i += if bytes[i] == b'\\' && end != b'\x03' { 2 } else { 1 };
         }
+        i + 1
+    }
+
+    fn peek_next(bytes: &[u8]) -> Option<u8> {
+        bytes
+            .iter()
+            .position(|b| !b.is_ascii_whitespace())
+            .map(|idx| bytes[idx])
+    }
+
+    /// Inserts `js` verbatim, e.g. callbacks such as `function(v) { return v + '%' }`
+    pub fn raw(js: impl Into<String>) -> Self {
+        let js = js.into().replace([RAW_START, RAW_END], "");
+        JS(format!("{RAW_START}{js}{RAW_END}"))
+    }
+
+    fn strip_raw(s: String) -> String {
+        s.replace([RAW_START, RAW_END], "")
     }
 
     pub fn dump(self) -> String {
-        self.0
+        Self::strip_raw(self.0)
+    }
+
+    // Object bodies end with a dangling separator after trailing commas or omitted optional keys
+    pub fn entries(self) -> String {
+        self.0.trim_end_matches([',', ' ']).to_string()
+    }
+
+    /// Strips all whitespace outside of string literals and raw js
+    pub fn minify(self) -> String {
+        Self::strip_raw(self.minify_keep_raw())
+    }
+
+    fn minify_keep_raw(self) -> String {
+        let mut js = String::with_capacity(self.0.len());
+        let (mut chars, mut quote) = (self.0.chars(), None);
+        while let Some(c) = chars.next() {
+            match (quote, c) {
+                (Some(_), '\\') => {
+                    js.push(c);
+                    js.extend(chars.next());
+                }
+                (Some(q), c) if c == q => {
+                    quote = None;
+                    js.push(c);
+                }
+                (Some(_), c) => js.push(c),
+                (None, '\'') => {
+                    quote = Some(c);
+                    js.push(c);
+                }
+                (None, RAW_START) => {
+                    quote = Some(RAW_END);
+                    js.push(c);
+                }
+                (None, c) if c.is_whitespace() => (),
+                (None, c) => js.push(c),
+            }
+        }
+        js
+    }
+
+    /// Converts to JSON, quoting keys and strings with double quotes and turning `undefined`
+    /// into `null`. Raw js has no JSON form, so it is kept as a string
+    pub fn json(self, pretty: bool) -> String {
+        let js = JS(self.minify_keep_raw());
+        let mut json = String::with_capacity(js.0.len());
+        let mut chars = js.0.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\'' => {
+                    let mut string = String::new();
+                    while let Some(c) = chars.next() {
+                        match c {
+                            '\'' => break,
+                            '\\' => string.push(Self::unescape(&mut chars)),
+                            c => string.push(c),
+                        }
+                    }
+                    json.push_str(&json_string(&string));
+                }
+                RAW_START => {
+                    let raw: String = chars.by_ref().take_while(|&c| c != RAW_END).collect();
+                    json.push_str(&json_string(&raw));
+                }
+                ',' if matches!(chars.peek(), Some('}' | ']') | None) => (),
+                ',' | ':' if pretty => json.extend([c, ' ']),
+                c if c.is_ascii_alphabetic() || c == '_' || c == '$' => {
+                    let word_char = |c: &&char| c.is_ascii_alphanumeric() || **c == '_';
+                    let mut word = c.to_string();
+                    while let Some(&c) = chars.peek().filter(word_char) {
+                        word.push(c);
+                        chars.next();
+                    }
+                    match (word.as_str(), chars.peek()) {
+                        (_, Some(':')) => json.push_str(&json_string(&word)),
+                        ("undefined", _) => json.push_str("null"),
+                        _ => json.push_str(&word),
+                    }
+                }
+                c if c.is_ascii_digit() || c == '-' => {
+                    let number_char = |c: &&char| c.is_ascii_alphanumeric() || "+-.".contains(**c);
+                    json.push(c);
+                    while let Some(&c) = chars.peek().filter(number_char) {
+                        json.push(c);
+                        chars.next();
+                    }
+                }
+                c => json.push(c),
+            }
+        }
+        match pretty {
+            true => JS(json).pretty(),
+            false => json,
+        }
+    }
+
+    /// Converts JSON into js, keys are unquoted and strings requoted just as with `js!`.
+    /// Strings found at one of the `raw` key paths, keys joined with dots and array indices
+    /// left out such as `"yaxis.labels.formatter"`, are inserted verbatim as raw js
+    pub fn from_json(json: &str, raw: &[&str]) -> Self {
+        let mut js = String::with_capacity(json.len());
+        // Key of each enclosing object, None for arrays and objects before their first key
+        let mut keys: Vec<Option<String>> = vec![];
+        let mut rest = json;
+        while let Some(c) = rest.chars().next() {
+            match c {
+                '"' => {
+                    let end = Self::literal_end(rest.as_bytes(), 0);
+                    let string: String = serde_json::from_str(&rest[..end]).expect("valid json string");
+                    rest = &rest[end..];
+                    if rest.starts_with(':') {
+                        js.push_str(&string);
+                        if let Some(key) = keys.last_mut() {
+                            *key = Some(string);
+                        }
+                        continue;
+                    }
+                    let path: Vec<&str> = keys.iter().flatten().map(String::as_str).collect();
+                    match raw.contains(&path.join(".").as_str()) {
+                        true => js.push_str(&JS::raw(string).0),
+                        false => js.push_str(&Self::transform(format!("{string:?}"))),
+                    }
+                    continue;
+                }
+                '{' | '[' => {
+                    keys.push(None);
+                    js.push(c);
+                }
+                '}' | ']' => {
+                    keys.pop();
+                    js.push(c);
+                }
+                ',' | ':' => js.extend([c, ' ']),
+                c => js.push(c),
+            }
+            rest = &rest[c.len_utf8()..];
+        }
+        JS(js)
+    }
+
+    // Character of the escape sequence following a backslash, in rust debug syntax
+    fn unescape(chars: &mut impl Iterator<Item = char>) -> char {
+        match chars.next() {
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('0') => '\0',
+            Some('u') => {
+                let hex: String = chars.take_while(|&c| c != '}').filter(|&c| c != '{').collect();
+                let code = u32::from_str_radix(&hex, 16).ok();
+                code.and_then(char::from_u32).unwrap_or(char::REPLACEMENT_CHARACTER)
+            }
+            Some(c) => c,
+            None => '\\',
+        }
     }
 
     pub fn pretty(self) -> String {
@@ -61,13 +268,28 @@ impl JS {
             bytes.splice(*at..*at + 1, prefix.into_bytes());
             *at += jump;
         };
-        let is_complex = |bytes: &[u8]| bytes.iter().any(|&b| b == b':' || b == b'{' || b == b'[');
+        let closing = |bytes: &[u8], mut at: usize| {
+            let (mut depth, mut complex) = (0, false);
+            while at < bytes.len() {
+                match bytes[at] {
+                    b'\'' | b'"' | b'\x02' => at = JS::literal_end(bytes, at) - 1,
+                    b']' if depth == 0 => break,
+                    b']' => depth -= 1,
+                    b'[' => (depth, complex) = (depth + 1, true),
+                    b':' | b'{' => complex = true,
+                    _ => (),
+                }
+                at += 1;
+            }
+            (at, complex)
+        };
 
         while i < bytes.len() {
             match bytes[i] {
+                b'\'' | b'"' | b'\x02' => i = JS::literal_end(&bytes, i),
                 b'[' => {
-                    let j = i + bytes[i..].iter().position(|&c| c == b']').unwrap();
-                    if is_complex(&bytes[i + 1..j]) {
+                    let (j, complex) = closing(&bytes, i + 1);
+                    if complex {
                         indent += 1;
                         apply_indent(&mut bytes, &mut i, format!("[{}", tab(indent)));
                     } else {
@@ -97,10 +319,29 @@ impl JS {
             }
         }
 
-        bytes.into_iter().map(|b| b as char).collect()
+        Self::strip_raw(String::from_utf8(bytes).unwrap())
     }
 }
 
+/// Quotes `s` as a JSON string
+pub fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
 #[macro_export]
 macro_rules! js {
     // Handle single keywords, literals and identifiers
@@ -118,30 +359,43 @@ macro_rules! js {
         $crate::js::JS::from(vec![$( format!("{:?}", $value), )*].join(", "))
     };
 
+    // Handle raw js values
+    ($key:ident : raw($value:expr)) => {
+        $crate::js::JS::from(format!("{}: {:?}", stringify!($key), $crate::js::JS::raw($value)))
+    };
+    ($key:ident : raw($value:expr), $($tail:tt)*) => {
+        $crate::js::JS::from(format!("{}: {:?}, {}", stringify!($key), $crate::js::JS::raw($value), js!($($tail)*)))
+    };
+
     // Handle trailing expressions
     ($key:ident : ($value:expr)) => {
         $crate::js::JS::from(format!("{}: {:?}", stringify!($key), $value))
     };
     ($key:ident : ($value:expr)?) => {
-        if $value.is_some() {
-            $crate::js::JS::from(format!("{}: {:?}", stringify!($key), $value))
-        } else {
-            $crate::js::JS::from(format!("{}: undefined", stringify!($key)))
+        match &$value {
+            Some(value) => $crate::js::JS::from(format!("{}: {:?}", stringify!($key), value)),
+            None => $crate::js::JS::from(format!("{}: undefined", stringify!($key))),
+        }
+    };
+    ($key:ident ?: ($value:expr)) => {
+        match &$value {
+            Some(value) => $crate::js::JS::from(format!("{}: {:?}", stringify!($key), value)),
+            None => $crate::js::JS::from(""),
         }
     };
     ($key:ident : $value:tt) => {{
         $crate::js::JS::from(format!("{}: {}", stringify!($key), js!($value)))
     }};
     ($key:ident : -$value:tt) => {{
-        $crate::js::JS::from(format!("{}: {}", stringify!($key), js!(-$value)))
+        $crate::js::JS::from(format!("{}: -{}", stringify!($key), js!($value)))
     }};
 
     // Handle trailing nested expressions
     ($key:ident : { $($body:tt)* }) => {
-        $crate::js::JS::from(format!("{}: {{{}}}", stringify!($key), js!($($body)*)))
+        $crate::js::JS::from(format!("{}: {{{}}}", stringify!($key), js!($($body)*).entries()))
     };
     ({ $($body:tt)* }) => {
-        $crate::js::JS::from(format!("{{{}}}", js!($($body)*)))
+        $crate::js::JS::from(format!("{{{}}}", js!($($body)*).entries()))
     };
     ($key:ident : [ $($body:tt)* ]) => {
         $crate::js::JS::from(format!("{}: [{}]", stringify!($key), js!($($body)*)))
@@ -155,25 +409,30 @@ macro_rules! js {
         $crate::js::JS::from(format!("{}: {:?}, {}", stringify!($key), $value, js!($($tail)*)))
     };
     ($key:ident : ($value:expr)?, $($tail:tt)*) => {
-        if $value.is_some() {
-            $crate::js::JS::from(format!("{}: {:?}, {}", stringify!($key), $value.unwrap(), js!($($tail)*)))
-        } else {
-            $crate::js::JS::from(format!("{}: undefined, {}", stringify!($key), js!($($tail)*)))
+        match &$value {
+            Some(value) => $crate::js::JS::from(format!("{}: {:?}, {}", stringify!($key), value, js!($($tail)*))),
+            None => $crate::js::JS::from(format!("{}: undefined, {}", stringify!($key), js!($($tail)*))),
+        }
+    };
+    ($key:ident ?: ($value:expr), $($tail:tt)*) => {
+        match &$value {
+            Some(value) => $crate::js::JS::from(format!("{}: {:?}, {}", stringify!($key), value, js!($($tail)*))),
+            None => js!($($tail)*),
         }
     };
     ($key:ident : $value:tt, $($tail:tt)*) => {
         $crate::js::JS::from(format!("{}: {}, {}", stringify!($key), js!($value), js!($($tail)*)))
     };
     ($key:ident : -$value:tt, $($tail:tt)*) => {
-        $crate::js::JS::from(format!("{}: {}, {}", stringify!($key), js!(-$value), js!($($tail)*)))
+        $crate::js::JS::from(format!("{}: -{}, {}", stringify!($key), js!($value), js!($($tail)*)))
     };
 
     // Handle consecutive nested expressions
     ($key:ident : { $($body:tt)* }, $($tail:tt)*) => {
-        $crate::js::JS::from(format!("{}: {{{}}}, {}", stringify!($key),  js!($($body)*), js!($($tail)*)))
+        $crate::js::JS::from(format!("{}: {{{}}}, {}", stringify!($key),  js!($($body)*).entries(), js!($($tail)*)))
     };
     ({ $($body:tt)* }, $($tail:tt)*) => {
-        $crate::js::JS::from(format!("{{{}}}, {}", js!($($body)*), js!($($tail)*)))
+        $crate::js::JS::from(format!("{{{}}}, {}", js!($($body)*).entries(), js!($($tail)*)))
     };
     ($key:ident : [ $($body:tt)* ], $($tail:tt)*) => {
         $crate::js::JS::from(format!("{}: [{}], {}", stringify!($key),  js!($($body)*), js!($($tail)*)))
@@ -185,6 +444,8 @@ macro_rules! js {
 
 #[cfg(test)]
 mod tests {
+    use super::JS;
+
     #[test]
     fn macro_test() {
         let string = "lorem ipsum";
@@ -210,6 +471,75 @@ mod tests {
         );
     }
 
+    #[test]
+    fn string_test() {
+        assert_eq!("t: 'a (b) c'", js!(t: "a (b) c").dump());
+        let label = String::from("Revenue (USD)");
+        assert_eq!("{title: 'Revenue (USD)'}", js!({ title: label }).dump());
+        assert_eq!("pair: ['(', 1]", js!(pair: (("(", 1))).dump());
+    }
+
+    #[test]
+    fn escape_test() {
+        let (quote, double, backslash) = ("O'Brien", "say \"hi\"", "C:\\data");
+        assert_eq!(r"name: 'O\'Brien'", js!(name: quote).dump());
+        assert_eq!(r#"name: 'say \"hi\"'"#, js!(name: double).dump());
+        assert_eq!(r"name: 'C:\\data'", js!(name: backslash).dump());
+        assert_eq!(r"{a: 'it\'s', b: ['O\'Brien']}", js!({a: "it's", b: [quote]}).dump());
+    }
+
+    #[test]
+    fn raw_test() {
+        let formatter = "function(v) { return v.toFixed(2) + '%' }";
+        assert_eq!(
+            "{labels: {formatter: function(v) { return v.toFixed(2) + '%' }}, a: 1}",
+            js!({labels: {formatter: raw(formatter)}, a: 1}).dump()
+        );
+        let raw = JS::raw("[1, 2].map((v) => v * 2)");
+        assert_eq!(
+            "{\n    data: [1, 2].map((v) => v * 2)\n}",
+            js!({ data: raw }).pretty()
+        );
+    }
+
+    #[test]
+    fn optional_test() {
+        let (some, none) = (Some(42), None::<i32>);
+        assert_eq!("{a: 42, c: 1}", js!({a?: (some), b?: (none), c: 1}).dump());
+        assert_eq!("{c: 1}", js!({b?: (none), c: 1}).dump());
+        assert_eq!("{a: 42}", js!({a?: (some)}).dump());
+        assert_eq!("{c: 1}", js!({c: 1, b?: (none)}).dump());
+    }
+
+    #[test]
+    fn minify_test() {
+        let js = || js!({a: null, b: [{x: 12.2, y: -32.4}], d: "lorem ipsum"});
+        let minified = js().minify();
+        assert_eq!("{a:null,b:[{x:12.2,y:-32.4}],d:'lorem ipsum'}", minified);
+        assert_eq!(JS::from(js().pretty()).minify(), minified);
+        assert_eq!("{f:v => v + 1}", js!({ f: raw("v => v + 1") }).minify());
+    }
+
+    #[test]
+    fn pretty_string_test() {
+        assert_eq!("d: 'contains ] and } chars'", js!(d: "contains ] and } chars").pretty());
+        assert_eq!(
+            "{\n    a: ['x]', 'y{'],\n    b: 'it\\'s [ok]'\n}",
+            js!({a: ["x]", "y{"], b: "it's [ok]"}).pretty()
+        );
+    }
+
+    #[test]
+    fn json_test() {
+        let name = "it's";
+        let none: Option<u8> = None;
+        let js = js!({ a: (name), b: [1, -2.5], c: (none)?, d: raw("(v) => v"), e: { f: true, }, });
+        assert_eq!(
+            js.json(false),
+            r#"{"a":"it's","b":[1,-2.5],"c":null,"d":"(v) => v","e":{"f":true}}"#
+        );
+    }
+
     #[test]
     fn pretty_test() {
         let js = js! {
@@ -249,4 +579,19 @@ mod tests {
 
         assert_eq!(js.pretty(), pretty_string);
     }
+
+    #[test]
+    fn undefined_test() {
+        let (some, none) = (Some(42), None::<i32>);
+        assert_eq!("a: 42", js!(a: (some)?).dump());
+        assert_eq!("a: undefined", js!(a: (none)?).dump());
+        assert_eq!("{a: 42, b: undefined, c: 1}", js!({a: (some)?, b: (none)?, c: 1}).dump());
+    }
+
+    #[test]
+    fn negative_test() {
+        assert_eq!("a: -1", js!(a: -1).dump());
+        assert_eq!("{a: -2.5, b: 1}", js!({a: -2.5, b: 1}).dump());
+        assert_eq!("[1, -2.5]", js!([1, -2.5]).dump());
+    }
 }
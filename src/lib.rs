@@ -1,5 +1,15 @@
 pub mod style;
 pub mod js;
 pub mod series;
+pub mod pie;
+pub mod heatmap;
 pub mod figure;
-pub mod webimg;
\ No newline at end of file
+pub mod options;
+pub mod axis;
+pub mod webimg;
+pub(crate) mod text;
+mod svg;
+#[cfg(feature = "io")]
+pub mod output;
+#[cfg(feature = "io")]
+pub mod browser;
\ No newline at end of file
@@ -0,0 +1,485 @@
+use crate::js::JS;
+use serde::{Serialize, Serializer};
+
+// ApexCharts options of series, pie and heatmap charts, serialized to json and then
+// converted to the js embedded in figures. Optional keys are left out instead of being set to null, since
+// ApexCharts treats null differently from a missing key
+
+/// Javascript such as formatter callbacks. It serializes as a plain string and is only
+/// inserted verbatim into the embedded js at the keys listed in `ChartOptions::RAW_PATHS`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Raw(pub String);
+
+impl Serialize for Raw {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+/// Either a size in pixels or a css size such as `"90%"`
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Size {
+    Pixels(usize),
+    Css(String),
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ChartOptions {
+    pub title: Title,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subtitle: Option<Title>,
+    pub chart: Chart,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<Theme>,
+    pub series: Vec<SeriesOptions>,
+    pub fill: Fill,
+    pub colors: Vec<String>,
+    pub markers: Markers,
+    pub stroke: Stroke,
+    pub data_labels: DataLabels,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grid: Option<Grid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub legend: Option<Legend>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plot_options: Option<PlotOptions>,
+    pub xaxis: XAxis,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub yaxis: Option<OneOrMany<YAxis>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tooltip: Option<Tooltip>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<Annotations>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub responsive: Option<Vec<Responsive>>,
+}
+
+impl ChartOptions {
+    /// Keys holding `Raw` js, every other string is quoted so that titles or series names
+    /// can't inject code
+    pub const RAW_PATHS: &'static [&'static str] =
+        &["dataLabels.formatter", "tooltip.y.formatter", "yaxis.labels.formatter"];
+
+    pub fn to_js(&self) -> JS {
+        let json = serde_json::to_string(self).expect("chart options are always serializable");
+        JS::from_json(&json, Self::RAW_PATHS)
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PieOptions {
+    pub title: Title,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subtitle: Option<Title>,
+    pub chart: Chart,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<Theme>,
+    pub series: Vec<f64>,
+    pub labels: Vec<String>,
+    pub colors: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_labels: Option<DataLabels>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub legend: Option<Legend>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub responsive: Option<Vec<Responsive>>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HeatmapOptions {
+    pub title: Title,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subtitle: Option<Title>,
+    pub chart: Chart,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<Theme>,
+    pub series: Vec<HeatmapRow>,
+    pub colors: Vec<String>,
+    pub data_labels: DataLabels,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub legend: Option<Legend>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plot_options: Option<HeatmapPlotOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub responsive: Option<Vec<Responsive>>,
+}
+
+/// Cells of one heatmap row, missing values are drawn as empty cells
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct HeatmapRow {
+    pub name: String,
+    pub data: Vec<HeatmapCell>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct HeatmapCell {
+    pub x: String,
+    pub y: Option<f64>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct HeatmapPlotOptions {
+    pub heatmap: HeatmapPlot,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HeatmapPlot {
+    pub color_scale: ColorScale,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ColorScale {
+    pub ranges: Vec<ColorRange>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ColorRange {
+    pub from: f64,
+    pub to: f64,
+    pub color: String,
+}
+
+// Options without any raw js
+pub(crate) fn to_js(options: &impl Serialize) -> JS {
+    let json = serde_json::to_string(options).expect("chart options are always serializable");
+    JS::from_json(&json, &[])
+}
+
+#[derive(Serialize, Default, Debug, Clone, PartialEq)]
+pub struct Title {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+impl Title {
+    pub fn new(text: impl Into<String>) -> Self {
+        Title { text: Some(text.into()) }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Toggle {
+    pub enabled: bool,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Chart {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(rename = "type")]
+    pub typ: String,
+    pub width: Size,
+    pub height: Size,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stacked: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub animations: Option<Toggle>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub brush: Option<Brush>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selection: Option<Toggle>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub zoom: Option<Zoom>,
+    pub toolbar: Toolbar,
+}
+
+/// Links a chart to the one with the `target` id, selecting a range zooms the target
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Brush {
+    pub target: String,
+    pub enabled: bool,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Zoom {
+    #[serde(rename = "type")]
+    pub typ: String,
+    pub enabled: bool,
+    pub auto_scale_yaxis: bool,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Toolbar {
+    pub show: bool,
+    pub auto_selected: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Tools>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Tools {
+    pub download: bool,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub mode: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SeriesOptions {
+    #[serde(rename = "type")]
+    pub typ: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub data: SeriesData,
+}
+
+/// Plain `[x, y]` points, points carrying goal markers or points with their own color. Missing
+/// y values are drawn as gaps
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum SeriesData {
+    Points(Vec<(f64, Option<f64>)>),
+    Goals(Vec<GoalPoint>),
+    Colored(Vec<ColoredPoint>),
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ColoredPoint {
+    pub x: f64,
+    pub y: Option<f64>,
+    pub fill_color: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct GoalPoint {
+    pub x: f64,
+    pub y: Option<f64>,
+    pub goals: Vec<Goal>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Goal {
+    pub name: String,
+    pub value: Option<f64>,
+    pub stroke_color: String,
+    pub stroke_height: usize,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Fill {
+    #[serde(rename = "type")]
+    pub typ: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub opacity: Option<Vec<f64>>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Markers {
+    pub shape: Vec<String>,
+    pub size: Vec<usize>,
+    pub fill_opacity: Vec<i32>,
+    pub stroke_colors: Vec<String>,
+    pub hover: MarkerHover,
+    pub radius: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub discrete: Option<Vec<DiscreteMarker>>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MarkerHover {
+    pub size_offset: usize,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscreteMarker {
+    pub series_index: usize,
+    pub data_point_index: usize,
+    pub fill_color: String,
+    pub stroke_color: String,
+    pub size: usize,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Stroke {
+    pub curve: Vec<String>,
+    pub width: Vec<usize>,
+    pub dash_array: Vec<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub colors: Option<Vec<String>>,
+    pub line_cap: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DataLabels {
+    pub enabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub formatter: Option<Raw>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Grid {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub show: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub border_color: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Legend {
+    pub show: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PlotOptions {
+    pub bar: Bar,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Bar {
+    pub horizontal: bool,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct XAxis {
+    #[serde(rename = "type")]
+    pub typ: String,
+    pub tick_placement: String,
+    pub tooltip: Toggle,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<Title>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct YAxis {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub series_name: Option<String>,
+    pub show: bool,
+    pub opposite: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<Title>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logarithmic: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_base: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub labels: Option<Formatted>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Tooltip {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x: Option<TooltipX>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub y: Option<Formatted>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TooltipX {
+    pub format: String,
+}
+
+/// Values passed through a javascript formatter
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Formatted {
+    pub formatter: Raw,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Annotations {
+    pub yaxis: Vec<YLine>,
+    pub xaxis: Vec<XLine>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct YLine {
+    pub y: f64,
+    pub label: Title,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct XLine {
+    pub x: f64,
+    pub label: Title,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Responsive {
+    pub breakpoint: usize,
+    pub options: ResponsiveOptions,
+}
+
+/// Overrides applied below the responsive breakpoint
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ResponsiveOptions {
+    pub legend: Legend,
+    pub title: Styled,
+    pub xaxis: Labels,
+    pub yaxis: Labels,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Labels {
+    pub labels: Styled,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Styled {
+    pub style: FontStyle,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FontStyle {
+    pub font_size: String,
+}
+
+impl Styled {
+    pub fn font_size(size: impl Into<String>) -> Self {
+        Styled { style: FontStyle { font_size: size.into() } }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize_test() {
+        let data = SeriesData::Points(vec![(0.0, Some(1.5)), (1.0, None)]);
+        let series = SeriesOptions { typ: "line".into(), name: None, data };
+        assert_eq!(
+            serde_json::to_string(&series).unwrap(),
+            r#"{"type":"line","data":[[0.0,1.5],[1.0,null]]}"#
+        );
+
+        let labels = DataLabels { enabled: true, formatter: Some(Raw("(v) => v".into())) };
+        assert_eq!(labels.formatter.as_ref().map(|raw| raw.0.as_str()), Some("(v) => v"));
+        let json = serde_json::to_string(&labels).unwrap();
+        assert_eq!(json, r#"{"enabled":true,"formatter":"(v) => v"}"#);
+        assert_eq!(JS::from_json(&json, &["formatter"]).dump(), "{enabled: true, formatter: (v) => v}");
+        assert_eq!(JS::from_json(&json, &[]).dump(), "{enabled: true, formatter: '(v) => v'}");
+    }
+}
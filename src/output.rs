@@ -0,0 +1,417 @@
+use io::Write;
+use std::{env, fs, io, path, process, thread, time};
+
+use crate::browser;
+use crate::figure::Figure;
+use image::imageops::FilterType;
+
+// Url of an absolute path, `file:///C:/dir/a.html` on Windows and `file:///dir/a.html`
+// elsewhere, with characters that have a meaning in urls escaped
+fn file_url(path: &path::Path) -> String {
+    let mut url = String::from("file://");
+    let mut path = path.display().to_string();
+    if !path.starts_with('/') {
+        path = format!("/{}", path.replace('\\', "/"));
+    }
+    for c in path.chars() {
+        match c {
+            '%' | ' ' | '#' | '?' => url.push_str(&format!("%{:02X}", c as u32)),
+            c => url.push(c),
+        }
+    }
+    url
+}
+
+impl Figure {
+    /// Writes the figure as a `width` x `height` png. Image figures are decoded and rescaled
+    /// directly, while charts are rendered with a headless chromium-based browser, which has
+    /// to be installed, see `browser::installed`
+    pub fn save_png(
+        &self,
+        path: impl AsRef<path::Path>,
+        width: u32,
+        height: u32,
+    ) -> Result<(), io::Error> {
+        let invalid = |err: image::ImageError| io::Error::new(io::ErrorKind::InvalidData, err);
+        match &self.image {
+            Some(encoded) => {
+                let bytes = base64::decode(encoded)
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                let mut image = image::load_from_memory(&bytes).map_err(invalid)?;
+                if (image.width(), image.height()) != (width, height) {
+                    image = image.resize_exact(width, height, FilterType::Lanczos3);
+                }
+                image.save_with_format(path, image::ImageFormat::Png).map_err(invalid)
+            }
+            None => self.screenshot(path.as_ref(), width, height),
+        }
+    }
+
+    fn screenshot(&self, path: &path::Path, width: u32, height: u32) -> Result<(), io::Error> {
+        let mut browsers = browser::installed();
+        browsers.retain(|browser| browser::is_chromium(browser));
+        if browsers.is_empty() {
+            let commands: Vec<_> = browser::chromium_commands().collect();
+            let message = format!(
+                "rendering charts to png requires a chromium-based browser, none of {} was found",
+                commands.join(", ")
+            );
+            return Err(io::Error::new(io::ErrorKind::NotFound, message));
+        }
+
+        let html = self.save_to(env::temp_dir())?;
+        let args = [
+            "--headless".to_string(),
+            "--disable-gpu".to_string(),
+            "--hide-scrollbars".to_string(),
+            "--virtual-time-budget=5000".to_string(),
+            format!("--window-size={width},{height}"),
+            format!("--screenshot={}", path.display()),
+            file_url(&html),
+        ];
+        let rendered = browsers.iter().any(|browser| {
+            process::Command::new(browser)
+                .args(&args)
+                .output()
+                .is_ok_and(|output| output.status.success())
+        });
+        fs::remove_file(html)?;
+
+        match rendered && path.is_file() {
+            true => Ok(()),
+            false => {
+                let tried: Vec<_> = browsers.iter().map(|b| b.display().to_string()).collect();
+                let message = format!("failed to render the chart with {}", tried.join(", "));
+                Err(io::Error::other(message))
+            }
+        }
+    }
+
+    /// Writes the html to exactly `path`, replacing any existing file
+    pub fn save_as(&self, path: impl AsRef<path::Path>) -> Result<(), io::Error> {
+        fs::write(path, self.html.as_bytes())
+    }
+
+    /// Writes the html to a new `{name}-{HHMMSSmmm}.html` file in `directory`. Figures saved
+    /// within the same millisecond get a numbered suffix instead of overwriting each other
+    pub fn save_to(&self, directory: impl AsRef<path::Path>) -> Result<path::PathBuf, io::Error> {
+        let directory = directory.as_ref();
+        if !directory.is_dir() {
+            let message = format!("not a directory: {}", directory.display());
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+        }
+        let stem = format!(
+            "{}-{}",
+            self.file_stem(),
+            chrono::Local::now().format("%H%M%S%3f")
+        );
+
+        for n in 0.. {
+            let path = match n {
+                0 => directory.join(format!("{stem}.html")),
+                n => directory.join(format!("{stem}-{n}.html")),
+            };
+            match fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    file.write_all(self.html.as_bytes())?;
+                    return Ok(path);
+                }
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(err),
+            }
+        }
+        unreachable!()
+    }
+    
+    /// Same as `save_to`, but creates the directory and its parents when they are missing
+    pub fn save_to_create(
+        &self,
+        directory: impl AsRef<path::Path>,
+    ) -> Result<path::PathBuf, io::Error> {
+        fs::create_dir_all(&directory)?;
+        self.save_to(directory)
+    }
+
+    // Figure name with characters that are not allowed in file names replaced
+    fn file_stem(&self) -> String {
+        let invalid = |c: char| c.is_control() || r#"/\:*?"<>|"#.contains(c);
+        self.name.replace(invalid, "_")
+    }
+
+    pub fn save(&self) -> Result<path::PathBuf, io::Error> {
+        self.save_to(&env::current_dir()?)
+    }
+    
+    fn webview(path: impl AsRef<path::Path>, browser: Option<&str>) -> Result<(), io::Error> {
+        let url = file_url(path.as_ref());
+        if let Some(browser) = browser.map(str::to_string).or_else(browser::from_env) {
+            return browser::launch(browser, &url);
+        }
+        if let Some(browser) = browser::detect() {
+            if browser::launch(browser, &url).is_ok() {
+                return Ok(());
+            }
+        }
+
+        // fall back to browsers the system may know how to start by name
+        let config = |browser| match browser {
+            "chrome" | "msedge" => format!("--app={url}"),
+            "firefox" => format!("-new-window {url}"),
+            _ => unreachable!(),
+        };
+        for browser in ["chrome", "msedge", "firefox"] {
+            if open::with(config(browser), browser).is_ok() {
+                return Ok(());
+            }
+        }
+        open::that(url)
+    }
+
+    /// Saves the figure to a temporary file and opens it as configured by `options`. The path
+    /// is returned when the file is kept for the caller to clean up
+    pub fn open_with_options(
+        &self,
+        options: &OpenOptions,
+    ) -> Result<Option<path::PathBuf>, io::Error> {
+        let path = self.save_to(env::temp_dir())?;
+        if let Err(err) = Self::webview(&path, options.browser.as_deref()) {
+            if options.cleanup {
+                fs::remove_file(&path)?;
+            }
+            return Err(err);
+        }
+        if options.wait_for_input {
+            println!("Press enter to continue...");
+            io::stdin().read_line(&mut String::new())?;
+        }
+
+        match (options.cleanup, options.wait_for_input) {
+            (false, _) => Ok(Some(path)),
+            (true, true) => fs::remove_file(path).map(|_| None),
+            (true, false) => {
+                let delay = options.cleanup_delay;
+                thread::spawn(move || {
+                    thread::sleep(delay);
+                    let _ = fs::remove_file(path);
+                });
+                Ok(None)
+            }
+        }
+    }
+
+    /// Opens the figure and blocks until the user presses enter, then removes the temporary file
+    pub fn open(&self) -> Result<(), io::Error> {
+        self.open_with_options(&OpenOptions::default()).map(|_| ())
+    }
+
+    /// Same as `open`, but with the given browser command or path instead of the detected one,
+    /// in app mode for chromium-based browsers
+    pub fn open_in(&self, browser: &str) -> Result<(), io::Error> {
+        let options = OpenOptions::default().with_browser(browser);
+        self.open_with_options(&options).map(|_| ())
+    }
+
+    /// Opens the figure without waiting for the user. The temporary html file is kept, so that
+    /// the browser has time to load it, and its path is returned for the caller to clean up
+    pub fn open_detached(&self) -> Result<path::PathBuf, io::Error> {
+        let options = OpenOptions::default().with_wait_for_input(false).with_cleanup(false);
+        let path = self.open_with_options(&options)?;
+        Ok(path.expect("files are kept without cleanup"))
+    }
+
+    /// Same as `open_detached`, but removes the temporary file after `delay` on a background
+    /// thread. The file is left behind if the program exits earlier
+    pub fn open_detached_with_cleanup(&self, delay: time::Duration) -> Result<(), io::Error> {
+        let options = OpenOptions::default()
+            .with_wait_for_input(false)
+            .with_cleanup_delay(delay);
+        self.open_with_options(&options).map(|_| ())
+    }
+}
+
+/// How `Figure::open_with_options` opens a figure. By default the detected browser is used,
+/// and the temporary html file is removed once the user presses enter
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpenOptions {
+    /// Browser command or path, when unset `AXOPLOTL_BROWSER` or else the detected browser is used
+    pub browser: Option<String>,
+    pub wait_for_input: bool,
+    pub cleanup: bool,
+    /// Time the browser has to load the file before it is removed, when not waiting for input
+    pub cleanup_delay: time::Duration,
+}
+
+impl Default for OpenOptions {
+    fn default() -> Self {
+        OpenOptions {
+            browser: None,
+            wait_for_input: true,
+            cleanup: true,
+            cleanup_delay: time::Duration::from_secs(10),
+        }
+    }
+}
+
+impl OpenOptions {
+    pub fn with_browser(mut self, browser: impl Into<String>) -> Self {
+        self.browser = Some(browser.into());
+        self
+    }
+
+    pub fn with_wait_for_input(mut self, wait: bool) -> Self {
+        self.wait_for_input = wait;
+        self
+    }
+
+    pub fn with_cleanup(mut self, cleanup: bool) -> Self {
+        self.cleanup = cleanup;
+        self
+    }
+
+    pub fn with_cleanup_delay(mut self, delay: time::Duration) -> Self {
+        self.cleanup_delay = delay;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::figure::FigureBuilder;
+    use crate::series::Series;
+    use crate::webimg::WebFormat;
+    use image::RgbImage;
+
+    fn figure() -> FigureBuilder<Vec<Series>> {
+        FigureBuilder::default().with_series(Series::new(&[0, 1], &[1, 2]))
+    }
+
+    #[test]
+    fn save_to_test() {
+        let fig = figure().with_title("a/b: c").build();
+        let missing = env::temp_dir().join("axoplotl-save-to-test").join("missing");
+        let err = fig.save_to(&missing).unwrap_err();
+        assert_eq!(err.to_string(), format!("not a directory: {}", missing.display()));
+
+        let path = fig.save_to_create(&missing).unwrap();
+        assert_eq!(path.parent(), Some(missing.as_path()));
+        assert!(path.file_name().unwrap().to_str().unwrap().starts_with("a_b_ c-"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), fig.html);
+        fs::remove_dir_all(missing.parent().unwrap()).unwrap();
+    }
+
+    // Browser stand-in recording the arguments it was launched with
+    #[cfg(unix)]
+    fn mock_browser(name: &str, command: &str) -> (path::PathBuf, path::PathBuf) {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = env::temp_dir().join(name);
+        fs::create_dir_all(&dir).unwrap();
+        let (browser, log) = (dir.join(command), dir.join("args.txt"));
+        let script = format!("#!/bin/sh\necho \"$@\" > {}.tmp && mv {0}.tmp {0}\n", log.display());
+        fs::write(&browser, script).unwrap();
+        fs::set_permissions(&browser, fs::Permissions::from_mode(0o755)).unwrap();
+        (browser, log)
+    }
+
+    #[cfg(unix)]
+    fn wait_until(condition: impl Fn() -> bool) -> bool {
+        (0..200).any(|_| {
+            thread::sleep(time::Duration::from_millis(25));
+            condition()
+        })
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn open_options_test() {
+        let (browser, log) = mock_browser("axoplotl-open-options-test", "mock-browser");
+        let fig = figure().with_title("opened").build();
+        let options = OpenOptions::default()
+            .with_browser(browser.to_str().unwrap())
+            .with_wait_for_input(false)
+            .with_cleanup(false);
+        let path = fig.open_with_options(&options).unwrap().unwrap();
+        assert!(wait_until(|| log.is_file()));
+        let args = fs::read_to_string(&log).unwrap();
+        assert_eq!(args.trim(), format!("-new-window file://{}", path.display()));
+        assert_eq!(fs::read_to_string(&path).unwrap(), fig.html);
+        fs::remove_file(&path).unwrap();
+
+        let options = options.with_cleanup(true).with_cleanup_delay(time::Duration::ZERO);
+        let kept = fig.open_with_options(&options).unwrap();
+        assert_eq!(kept, None);
+        assert!(wait_until(|| {
+            let args = fs::read_to_string(&log).unwrap_or_default();
+            let opened = args.trim().trim_start_matches("-new-window file://").to_string();
+            opened != path.display().to_string() && !path::Path::new(&opened).exists()
+        }));
+        fs::remove_dir_all(browser.parent().unwrap()).unwrap();
+    }
+
+    // The only test setting the override, the others pass their browser explicitly
+    #[cfg(unix)]
+    #[test]
+    fn browser_override_test() {
+        let (browser, log) = mock_browser("axoplotl-browser-override-test", "chromium");
+        env::set_var(browser::BROWSER_VAR, &browser);
+        let fig = figure().with_title("overridden").build();
+        let options = OpenOptions::default().with_wait_for_input(false).with_cleanup(false);
+        let path = fig.open_with_options(&options);
+        env::remove_var(browser::BROWSER_VAR);
+
+        let path = path.unwrap().unwrap();
+        assert!(wait_until(|| log.is_file()));
+        let args = fs::read_to_string(&log).unwrap();
+        assert_eq!(args.trim(), format!("--app=file://{}", path.display()));
+        fs::remove_file(&path).unwrap();
+        fs::remove_dir_all(browser.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn file_url_test() {
+        assert_eq!(file_url(path::Path::new("/tmp/a b#1.html")), "file:///tmp/a%20b%231.html");
+        assert_eq!(file_url(path::Path::new(r"C:\Users\me\a.html")), "file:///C:/Users/me/a.html");
+    }
+
+    #[test]
+    fn save_as_test() {
+        let fig = figure().build();
+        let path = env::temp_dir().join("axoplotl-save-as-test.html");
+        fs::write(&path, "stale").unwrap();
+        fig.save_as(&path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), fig.html);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn save_to_collision_test() {
+        let fig = figure().with_title("collision").build();
+        let dir = env::temp_dir().join("axoplotl-save-to-collision-test");
+        fs::create_dir_all(&dir).unwrap();
+
+        let paths: Vec<_> = (0..5).map(|_| fig.save_to(&dir).unwrap()).collect();
+        for (i, path) in paths.iter().enumerate() {
+            assert!(!paths[..i].contains(path));
+            assert_eq!(fs::read_to_string(path).unwrap(), fig.html);
+        }
+        let name = paths[0].file_name().unwrap().to_str().unwrap();
+        assert_eq!(name.len(), "collision-HHMMSSmmm.html".len());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn save_png_test() {
+        let image = RgbImage::from_fn(8, 4, |x, _| image::Rgb([x as u8 * 30, 0, 0]));
+        let path = env::temp_dir().join("axoplotl-save-png-test.png");
+
+        let fig = FigureBuilder::new("img", 8, 4, &image).build();
+        fig.save_png(&path, 8, 4).unwrap();
+        assert_eq!(image::open(&path).unwrap().to_rgb8(), image);
+
+        let jpeg = FigureBuilder::new("img", 8, 4, &image).with_format(WebFormat::Jpeg(90));
+        jpeg.build().save_png(&path, 4, 2).unwrap();
+        let saved = image::open(&path).unwrap();
+        assert_eq!((saved.width(), saved.height()), (4, 2));
+        fs::remove_file(path).unwrap();
+    }
+}
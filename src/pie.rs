@@ -0,0 +1,39 @@
+/// Labeled values plotted as slices of a pie or donut chart
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Pie {
+    slices: Vec<(String, f64)>,
+    pub donut: bool,
+}
+
+impl Pie {
+    pub fn new<S, T>(slices: &[(S, T)]) -> Self
+    where
+        S: AsRef<str>,
+        T: Into<f64> + Copy,
+    {
+        slices
+            .iter()
+            .fold(Pie::default(), |pie, (label, value)| pie.with_slice(label.as_ref(), *value))
+    }
+
+    pub fn slices(&self) -> &[(String, f64)] {
+        &self.slices
+    }
+
+    pub fn with_slice(mut self, label: impl Into<String>, value: impl Into<f64>) -> Self {
+        self.slices.push((label.into(), value.into()));
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slices_test() {
+        let pie = Pie::new(&[("a", 1), ("b", 2)]);
+        assert_eq!(pie, Pie::default().with_slice("a", 1).with_slice("b", 2));
+        assert_eq!(pie.slices()[1], ("b".to_string(), 2.0));
+    }
+}
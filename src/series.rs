@@ -1,4 +1,35 @@
+use crate::axis::AxisRef;
 use crate::style::*;
+use std::fmt;
+
+/// How points with NaN or infinite values are plotted
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub enum NonFinite {
+    /// Non-finite y values are emitted as `null`, breaking the line at that point
+    #[default]
+    Gap,
+    /// Points with a non-finite x or y are removed
+    Drop,
+}
+
+/// Series whose x and y were set to different lengths with `with_x` and `with_y`, or whose
+/// errors don't match y
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LengthMismatch {
+    Axes { x: usize, y: usize },
+    YErr { y: usize, yerr: usize },
+}
+
+impl fmt::Display for LengthMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Axes { x, y } => write!(f, "x and y have different lengths ({x} and {y})"),
+            Self::YErr { y, yerr } => write!(f, "y and yerr have different lengths ({y} and {yerr})"),
+        }
+    }
+}
+
+impl std::error::Error for LengthMismatch {}
 
 #[derive(Default, Debug, Clone, PartialEq)]
 pub struct Series {
@@ -6,6 +37,12 @@ pub struct Series {
     y: Vec<f64>,
     pub style: Style,
     pub name: Option<String>,
+    y_axis: Option<AxisRef>,
+    endpoint_marker: bool,
+    time_x: bool,
+    yerr: Option<Vec<f64>>,
+    point_colors: Option<Vec<String>>,
+    non_finite: NonFinite,
 }
 
 impl Series {
@@ -17,6 +54,49 @@ impl Series {
         Series::default().with_data(x, y)
     }
 
+    /// Series from `(x, y)` pairs or `[x, y]` arrays
+    pub fn from_points<I, P>(points: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<[f64; 2]>,
+    {
+        let (x, y): (Vec<f64>, Vec<f64>) = points
+            .into_iter()
+            .map(|point| {
+                let [x, y] = point.into();
+                (x, y)
+            })
+            .unzip();
+        Series::new(&x, &y)
+    }
+
+    /// Series with x given as unix timestamps in milliseconds, plotted on a datetime x-axis
+    pub fn new_timeseries<U>(millis: &[i64], y: &[U]) -> Self
+    where
+        U: Into<f64> + Copy,
+    {
+        let x: Vec<f64> = millis.iter().map(|&ms| ms as f64).collect();
+        Series::new(&x, y).with_time_x(true)
+    }
+
+    pub fn from_datetimes<Tz, U>(x: &[chrono::DateTime<Tz>], y: &[U]) -> Self
+    where
+        Tz: chrono::TimeZone,
+        U: Into<f64> + Copy,
+    {
+        let millis: Vec<i64> = x.iter().map(|dt| dt.timestamp_millis()).collect();
+        Series::new_timeseries(&millis, y)
+    }
+
+    /// Number of points, taken from x
+    pub fn len(&self) -> usize {
+        self.x.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.x.is_empty()
+    }
+
     pub fn data(&self) -> Vec<[&f64; 2]> {
         self.x
             .iter()
@@ -25,24 +105,120 @@ impl Series {
             .collect()
     }
 
+    /// Panics when x and y have different lengths, see `validate`
     pub fn into_data(self) -> Vec<[f64; 2]> {
+        self.assert_valid();
         self.x
             .into_iter()
-            .zip(self.y.into_iter())
+            .zip(self.y)
             .map(|(x, y)| [x, y])
             .collect()
     }
 
+    /// Appends a single point, e.g. for live plots that re-render as data arrives. Points with
+    /// error bars get a zero error and colored points repeat the last color
+    pub fn push(&mut self, x: f64, y: f64) {
+        self.x.push(x);
+        self.y.push(y);
+        if let Some(yerr) = &mut self.yerr {
+            yerr.push(0.0);
+        }
+        if let Some(colors) = &mut self.point_colors {
+            let last = colors.last().cloned().unwrap_or_default();
+            colors.push(last);
+        }
+    }
+
     pub fn with_data<T, U>(mut self, x: &[T], y: &[U]) -> Self
     where
         T: Into<f64> + Copy,
         U: Into<f64> + Copy,
     {
-        // TODO: resample x depending on difference of lengths between x and y
         assert_eq!(x.len(), y.len(), "x and y have different lengths");
 
         self.x = x.iter().map(|&v| v.into()).collect();
         self.y = y.iter().map(|&v| v.into()).collect();
+        self.drop_stale_per_point();
+        self
+    }
+
+    /// Sets x alone. Unlike `with_data` the lengths are not checked here, a mismatch with y is
+    /// only an error once the figure is rendered
+    pub fn with_x<T: Into<f64> + Copy>(mut self, x: &[T]) -> Self {
+        self.x = x.iter().map(|&v| v.into()).collect();
+        self
+    }
+
+    /// Sets y alone, see `with_x`
+    pub fn with_y<U: Into<f64> + Copy>(mut self, y: &[U]) -> Self {
+        self.y = y.iter().map(|&v| v.into()).collect();
+        self.drop_stale_per_point();
+        self
+    }
+
+    // Errors and point colors are set for the previous y, replacing it with a different length
+    // unsets them
+    fn drop_stale_per_point(&mut self) {
+        let len = self.y.len();
+        if self.yerr.as_ref().is_some_and(|yerr| yerr.len() != len) {
+            self.yerr = None;
+        }
+        if self.point_colors.as_ref().is_some_and(|colors| colors.len() != len) {
+            self.point_colors = None;
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), LengthMismatch> {
+        let (x, y) = (self.x.len(), self.y.len());
+        if x != y {
+            return Err(LengthMismatch::Axes { x, y });
+        }
+        match self.yerr.as_ref().map(Vec::len) {
+            Some(yerr) if yerr != y => Err(LengthMismatch::YErr { y, yerr }),
+            _ => Ok(()),
+        }
+    }
+
+    fn assert_valid(&self) {
+        if let Err(err) = self.validate() {
+            panic!("{err}");
+        }
+    }
+
+    /// Same as `with_data`, but accepts axes of different lengths. The shorter axis is kept
+    /// as is, while the longer one is linearly resampled down to the same number of evenly
+    /// spaced points spanning its whole range
+    pub fn with_data_resampled<T, U>(self, x: &[T], y: &[U]) -> Self
+    where
+        T: Into<f64> + Copy,
+        U: Into<f64> + Copy,
+    {
+        let x: Vec<f64> = x.iter().map(|&v| v.into()).collect();
+        let y: Vec<f64> = y.iter().map(|&v| v.into()).collect();
+        let len = x.len().min(y.len());
+        self.with_data(&resample(&x, len), &resample(&y, len))
+    }
+
+    /// Downsamples the series to at most `max_points` with largest-triangle-three-buckets,
+    /// which keeps the visual shape of the line. Series that are already short enough, or
+    /// limits below 3 points are left untouched
+    ///
+    /// # Panics
+    /// When the series is invalid, e.g. x and y have different lengths
+    pub fn decimate(mut self, max_points: usize) -> Self {
+        self.assert_valid();
+        if max_points >= self.len() || max_points < 3 {
+            return self;
+        }
+        let indices = lttb(&self.x, &self.y, max_points);
+        let pick = |values: &[f64]| indices.iter().map(|&i| values[i]).collect();
+        self.x = pick(&self.x);
+        self.y = pick(&self.y);
+        self.yerr = self.yerr.as_deref().map(pick);
+        self.point_colors = self
+            .point_colors
+            .as_deref()
+            .map(|colors| indices.iter().map(|&i| colors[i].clone()).collect());
         self
     }
 
@@ -55,10 +231,258 @@ impl Series {
         self.name = Some(name.into());
         self
     }
+
+    pub fn yerr(&self) -> Option<&[f64]> {
+        self.yerr.as_deref()
+    }
+
+    /// Symmetric vertical error for each data point, drawn as low and high markers at
+    /// `y - err` and `y + err`. ApexCharts only draws them on columns, so other series types
+    /// ignore the error. Setting y of a different length afterwards unsets them
+    pub fn with_yerr<T>(mut self, yerr: &[T]) -> Self
+    where
+        T: Into<f64> + Copy,
+    {
+        assert_eq!(yerr.len(), self.y.len(), "y and yerr have different lengths");
+        self.yerr = Some(yerr.iter().map(|&v| v.into()).collect());
+        self
+    }
+
+    pub fn point_colors(&self) -> Option<&[String]> {
+        self.point_colors.as_deref()
+    }
+
+    /// Marker color for each data point, e.g. to encode a third variable. Only scatter series,
+    /// drawn with markers and without lines, use them. Setting y of a different length afterwards
+    /// unsets them
+    pub fn with_point_colors(mut self, colors: &[&str]) -> Self {
+        assert_eq!(colors.len(), self.y.len(), "y and point colors have different lengths");
+        self.point_colors = Some(colors.iter().map(|c| c.to_string()).collect());
+        self
+    }
+
+    pub fn non_finite(&self) -> NonFinite {
+        self.non_finite
+    }
+
+    pub fn with_non_finite(mut self, non_finite: NonFinite) -> Self {
+        self.non_finite = non_finite;
+        self
+    }
+
+    /// Removes the points that can't be plotted, those with a non-finite x, and with
+    /// `NonFinite::Drop` also those with a non-finite y. Panics when x and y have different
+    /// lengths, see `validate`
+    pub fn cleaned(mut self) -> Self {
+        self.assert_valid();
+        let keep: Vec<bool> = self
+            .x
+            .iter()
+            .zip(&self.y)
+            .map(|(x, y)| x.is_finite() && (y.is_finite() || self.non_finite == NonFinite::Gap))
+            .collect();
+        let retain = |values: &mut Vec<f64>| {
+            let mut keep = keep.iter();
+            values.retain(|_| *keep.next().unwrap());
+        };
+        retain(&mut self.x);
+        retain(&mut self.y);
+        if let Some(yerr) = self.yerr.as_mut() {
+            retain(yerr);
+        }
+        if let Some(colors) = self.point_colors.as_mut() {
+            let mut keep = keep.iter();
+            colors.retain(|_| *keep.next().unwrap());
+        }
+        self
+    }
+
+    pub fn endpoint_marker(&self) -> bool {
+        self.endpoint_marker
+    }
+
+    /// Draws the series marker only on the last data point
+    pub fn with_endpoint_marker(mut self, enabled: bool) -> Self {
+        self.endpoint_marker = enabled;
+        self
+    }
+
+    pub fn time_x(&self) -> bool {
+        self.time_x
+    }
+
+    /// Treats x values as unix timestamps in milliseconds
+    pub fn with_time_x(mut self, time_x: bool) -> Self {
+        self.time_x = time_x;
+        self
+    }
+
+    pub fn y_axis(&self) -> Option<&AxisRef> {
+        self.y_axis.as_ref()
+    }
+
+    /// Assigns the series to a y-axis by its position, axes past the first one are drawn on the
+    /// opposite side unless registered on the figure with a different config
+    pub fn with_y_axis(mut self, axis: usize) -> Self {
+        self.y_axis = Some(AxisRef::Index(axis));
+        self
+    }
+
+    /// Assigns the series to a y-axis registered on the figure with `FigureBuilder::add_y_axis`
+    pub fn with_y_axis_name(mut self, axis: impl Into<String>) -> Self {
+        self.y_axis = Some(AxisRef::Name(axis.into()));
+        self
+    }
+}
+
+fn resample(values: &[f64], len: usize) -> Vec<f64> {
+    if values.len() == len {
+        return values.to_vec();
+    }
+    let step = match len {
+        0 | 1 => 0.0,
+        _ => (values.len() - 1) as f64 / (len - 1) as f64,
+    };
+    (0..len)
+        .map(|i| {
+            let pos = i as f64 * step;
+            let (lo, t) = (pos.floor() as usize, pos.fract());
+            match values.get(lo + 1) {
+                Some(next) => values[lo] + (next - values[lo]) * t,
+                None => values[lo],
+            }
+        })
+        .collect()
+}
+
+// Indices of the points kept, the first and last are always kept and each bucket in between
+// contributes the point forming the largest triangle with the previous pick and the average of
+// the next bucket
+fn lttb(x: &[f64], y: &[f64], threshold: usize) -> Vec<usize> {
+    let len = x.len();
+    let every = (len - 2) as f64 / (threshold - 2) as f64;
+    let bucket = |i: usize| (i as f64 * every) as usize + 1;
+
+    let mut picked = Vec::with_capacity(threshold);
+    let mut a = 0;
+    picked.push(a);
+    for i in 0..threshold - 2 {
+        let next = bucket(i + 1)..bucket(i + 2).min(len);
+        let count = next.len() as f64;
+        let avg_x = x[next.clone()].iter().sum::<f64>() / count;
+        let avg_y = y[next].iter().sum::<f64>() / count;
+
+        let area = |j: usize| {
+            ((x[a] - avg_x) * (y[j] - y[a]) - (x[a] - x[j]) * (avg_y - y[a])).abs()
+        };
+        a = (bucket(i)..bucket(i + 1))
+            .max_by(|&j, &k| area(j).total_cmp(&area(k)))
+            .unwrap();
+        picked.push(a);
+    }
+    picked.push(len - 1);
+    picked
 }
 
+impl Extend<(f64, f64)> for Series {
+    fn extend<I: IntoIterator<Item = (f64, f64)>>(&mut self, points: I) {
+        for (x, y) in points {
+            self.push(x, y);
+        }
+    }
+}
+
+/// Series plotted against its indices
+impl From<Vec<f64>> for Series {
+    fn from(y: Vec<f64>) -> Self {
+        let x = (0..y.len()).map(|v| v as f64).collect();
+        Series { x, y, ..Default::default() }
+    }
+}
+
+/// Panics when x and y have different lengths, same as `Series::new`
+impl From<(Vec<f64>, Vec<f64>)> for Series {
+    fn from((x, y): (Vec<f64>, Vec<f64>)) -> Self {
+        assert_eq!(x.len(), y.len(), "x and y have different lengths");
+        Series { x, y, ..Default::default() }
+    }
+}
+
+#[cfg(feature = "ndarray")]
+impl Series {
+    pub fn from_ndarray<S, T>(y: &ndarray::ArrayBase<S, ndarray::Ix1>) -> Self
+    where
+        S: ndarray::Data<Elem = T>,
+        T: Into<f64> + Copy,
+    {
+        let x: Vec<f64> = (0..y.len()).map(|v| v as f64).collect();
+        let y: Vec<T> = y.iter().copied().collect();
+        Series::new(&x, &y)
+    }
+}
+
+#[cfg(feature = "ndarray")]
+impl<S, T> From<&ndarray::ArrayBase<S, ndarray::Ix1>> for Series
+where
+    S: ndarray::Data<Elem = T>,
+    T: Into<f64> + Copy,
+{
+    fn from(y: &ndarray::ArrayBase<S, ndarray::Ix1>) -> Self {
+        Series::from_ndarray(y)
+    }
+}
+
+#[cfg(feature = "nalgebra")]
+impl Series {
+    pub fn from_nalgebra<T, R, S>(y: &nalgebra::Matrix<T, R, nalgebra::U1, S>) -> Self
+    where
+        T: nalgebra::Scalar + Into<f64> + Copy,
+        R: nalgebra::Dim,
+        S: nalgebra::RawStorage<T, R>,
+    {
+        let x: Vec<f64> = (0..y.len()).map(|v| v as f64).collect();
+        let y: Vec<T> = y.iter().copied().collect();
+        Series::new(&x, &y)
+    }
+}
+
+#[cfg(feature = "nalgebra")]
+impl<T, R, S> From<&nalgebra::Matrix<T, R, nalgebra::U1, S>> for Series
+where
+    T: nalgebra::Scalar + Into<f64> + Copy,
+    R: nalgebra::Dim,
+    S: nalgebra::RawStorage<T, R>,
+{
+    fn from(y: &nalgebra::Matrix<T, R, nalgebra::U1, S>) -> Self {
+        Series::from_nalgebra(y)
+    }
+}
+
+/// Builds a named series from a y variable, with optional x values and style:
+/// `series!(y)`, `series!(y, "style")`, `series!(x, y)`, `series!(x, y, "style")`. The x values
+/// default to `0..y.len()`. A `Style` value is passed with a `style =` prefix, as in
+/// `series!(y, style = my_style)`, since any expression could also be the x values.
+/// The arguments may also be wrapped in a tuple, `series!((x, y, "style"))`, which lets `plot!`
+/// take any x expression or style value
 #[macro_export]
 macro_rules! series {
+    (($($args:tt)*)) => {{
+        $crate::series!($($args)*)
+    }};
+
+    ($y:ident, style = $style:expr) => {{
+        let x: Vec<f64> = (0..$y.len()).map(|v| v as f64).collect();
+        $crate::series::Series::new(&x, &$y)
+            .with_name(stringify!($y))
+            .with_style($style)
+    }};
+
+    ($x:expr, $y:ident, style = $style:expr) => {{
+        $crate::series::Series::new(&$x, &$y)
+            .with_name(stringify!($y))
+            .with_style($style)
+    }};
+
     ($y:ident) => {{
         let x: Vec<f64> = (0..$y.len()).map(|v| v as f64).collect();
         $crate::series::Series::new(&x, &$y).with_name(stringify!($y))
@@ -68,31 +492,226 @@ macro_rules! series {
         $crate::series::Series::new(&$x, &$y).with_name(stringify!($y))
     }};
 
-    // ($y:ident, $style:expr) => {{
-    //     let x: Vec<f64> = (0..$y.len()).map(|v| v as f64).collect();
-    //     $crate::series::Series::new(&x, &$y)
-    //         .with_name(stringify!($y))
-    //         .with_style($style)
-    // }};
-
     ($y:ident, $style:literal) => {{
-        let s = $crate::style::Style::from($style);
-        let x: Vec<f64> = (0..$y.len()).map(|v| v as f64).collect();
-        $crate::series::Series::new(&x, &$y)
-            .with_name(stringify!($y))
-            .with_style(s)
+        $crate::series!($y, style = $crate::style::Style::from($style))
     }};
 
-    // ($x:expr, $y:ident, $style:expr) => {{
-    //     $crate::series::Series::new(&$x, &$y)
-    //         .with_name(stringify!($y))
-    //         .with_style($style)
-    // }};
-
     ($x:expr, $y:ident, $style:literal) => {{
-        let s = $crate::style::Style::from($style);
-        $crate::series::Series::new(&$x, &$y)
-            .with_name(stringify!($y))
-            .with_style(s)
+        $crate::series!($x, $y, style = $crate::style::Style::from($style))
     }};
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resampled_test() {
+        let x_longer = Series::default().with_data_resampled(&[0, 1, 2, 3, 4], &[10, 20, 30]);
+        assert_eq!(x_longer, Series::new(&[0, 2, 4], &[10, 20, 30]));
+
+        let y_longer = Series::default().with_data_resampled(&[0, 10], &[1.0, 2.0, 4.0, 8.0]);
+        assert_eq!(y_longer, Series::new(&[0, 10], &[1, 8]));
+
+        let uneven = Series::default().with_data_resampled(&[0, 1, 2], &[0, 3, 4, 8, 9]);
+        assert_eq!(uneven, Series::new(&[0, 1, 2], &[0, 4, 9]));
+
+        let y = [1.0, 2.0, 3.0, 4.0];
+        let odd = Series::default().with_data_resampled(&[0, 1, 2], &y);
+        assert_eq!(odd, Series::new(&[0, 1, 2], &[1.0, 2.5, 4.0]));
+
+        assert_eq!(Series::default().with_data_resampled(&[0], &y), Series::new(&[0], &[1]));
+        assert_eq!(Series::default().with_data_resampled::<f64, _>(&[], &y), Series::default());
+    }
+
+    #[test]
+    fn points_test() {
+        let points = vec![(0.0, 1.0), (1.0, 4.0), (2.0, 9.0)];
+        let expected = Series::new(&[0, 1, 2], &[1, 4, 9]);
+        assert_eq!(Series::from_points(points), expected);
+        assert_eq!(Series::from_points([[0.0, 1.0], [1.0, 4.0], [2.0, 9.0]]), expected);
+        let squares = (0..3).map(|x| (x as f64, (x * x + 1) as f64));
+        assert_eq!(Series::from_points(squares).data()[2], [&2.0, &5.0]);
+    }
+
+    #[test]
+    fn non_finite_test() {
+        let series = Series::new(&[0.0, 1.0, 2.0, f64::NAN], &[1.0, f64::NAN, 3.0, 4.0]);
+        let gap = series.clone().cleaned().into_data();
+        assert_eq!(gap.len(), 3);
+        assert!(gap[1][1].is_nan());
+
+        let dropped = series.with_non_finite(NonFinite::Drop).with_yerr(&[1, 2, 3, 4]);
+        let dropped = dropped.cleaned();
+        assert_eq!(dropped.into_data(), vec![[0.0, 1.0], [2.0, 3.0]]);
+    }
+
+    #[test]
+    fn decimate_test() {
+        let x: Vec<f64> = (0..10_000).map(|i| i as f64 / 100.0).collect();
+        let y: Vec<f64> = x.iter().map(|x| x.sin()).collect();
+        let series = Series::new(&x, &y).decimate(500);
+        assert_eq!(series.data().len(), 500);
+
+        let max = series.y.iter().copied().fold(f64::MIN, f64::max);
+        let min = series.y.iter().copied().fold(f64::MAX, f64::min);
+        assert!((max - 1.0).abs() < 1e-3 && (min + 1.0).abs() < 1e-3);
+        assert_eq!(series.x[0], 0.0);
+        assert_eq!(series.x[499], 99.99);
+
+        let short = Series::new(&[0, 1, 2], &[1, 2, 3]);
+        assert_eq!(short.clone().decimate(500), short);
+    }
+
+    #[test]
+    #[should_panic(expected = "x and y have different lengths")]
+    fn decimate_mismatched_test() {
+        let series = Series::default().with_x(&[0, 1, 2, 3, 4]).with_y(&[1, 2, 3, 4]);
+        series.decimate(3);
+    }
+
+    #[test]
+    fn macro_test() {
+        let (x, y) = (vec![10, 20], [1, 2]);
+        let expected = Series::new(&x, &y).with_name("y");
+        assert_eq!(series!((x.clone(), y)), expected);
+        assert_eq!(series!((vec![10, 20], y, "r")), expected.clone().with_style("r".into()));
+        assert_eq!(series!(y), Series::new(&[0, 1], &y).with_name("y"));
+
+        let style = Style::default().with_color("red").with_marker(("square", 6, false));
+        let expected = expected.with_style(style.clone());
+        assert_eq!(series!(x, y, style = style.clone()), expected);
+        assert_eq!(series!((x, y, style = style.clone())), expected);
+        let indexed = series!(y, style = style);
+        assert_eq!(indexed, expected.with_data(&[0, 1], &y));
+    }
+
+    #[test]
+    fn timeseries_test() {
+        let series = Series::new_timeseries(&[1_700_000_000_000, 1_700_000_060_000], &[1, 2]);
+        assert!(series.time_x());
+        assert_eq!(series.data()[1], [&1_700_000_060_000.0, &2.0]);
+
+        let dt = chrono::TimeZone::timestamp_millis_opt(&chrono::Utc, 1_700_000_000_000).unwrap();
+        assert_eq!(
+            Series::from_datetimes(&[dt], &[5]),
+            Series::new_timeseries(&[dt.timestamp_millis()], &[5])
+        );
+        assert!(!Series::new(&[0], &[1]).time_x());
+    }
+
+    #[test]
+    #[should_panic(expected = "y and yerr have different lengths")]
+    fn yerr_test() {
+        let series = Series::new(&[0, 1], &[1, 2]).with_yerr(&[0.5, 0.25]);
+        assert_eq!(series.yerr(), Some([0.5, 0.25].as_slice()));
+        series.with_yerr(&[1]);
+    }
+
+    #[test]
+    fn len_test() {
+        assert!(Series::default().is_empty());
+        let series = Series::new(&[0, 1, 2], &[1, 2, 3]);
+        assert_eq!(series.len(), 3);
+        assert!(!series.is_empty());
+    }
+
+    #[test]
+    fn point_colors_test() {
+        let series = Series::new(&[0.0, f64::NAN, 2.0], &[1, 2, 3])
+            .with_point_colors(&["red", "green", "blue"]);
+        let cleaned = series.clone().cleaned();
+        assert_eq!(cleaned.point_colors(), Some(&["red".to_string(), "blue".to_string()][..]));
+        assert_eq!(series.clone().with_y(&[1, 2, 3]).point_colors().map(<[_]>::len), Some(3));
+        assert_eq!(series.clone().with_y(&[1, 2]).point_colors(), None);
+        assert_eq!(series.with_data_resampled(&[0, 1, 2, 3], &[1, 2]).point_colors(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "y and point colors have different lengths")]
+    fn point_colors_mismatch_test() {
+        Series::new(&[0, 1, 2], &[1, 2, 3]).with_point_colors(&["red"]);
+    }
+
+    #[test]
+    fn push_test() {
+        let mut series = Series::new(&[0.0], &[1.0]).with_yerr(&[0.5]);
+        series.push(1.0, 2.0);
+        series.extend([(2.0, 4.0), (3.0, 8.0)]);
+        assert_eq!(series.yerr(), Some(&[0.5, 0.0, 0.0, 0.0][..]));
+        assert_eq!(
+            series.into_data(),
+            vec![[0.0, 1.0], [1.0, 2.0], [2.0, 4.0], [3.0, 8.0]]
+        );
+    }
+
+    #[test]
+    fn stale_yerr_test() {
+        let series = Series::new(&[0, 1, 2], &[1, 2, 3]).with_yerr(&[0.1, 0.2, 0.3]);
+        let same = series.clone().with_y(&[4, 5, 6]);
+        assert_eq!(same.yerr(), Some(&[0.1, 0.2, 0.3][..]));
+        let longer = series.clone().with_y(&[1, 2, 3, 4, 5]).with_x(&[0, 1, 2, 3, 4]);
+        assert_eq!(longer.yerr(), None);
+        assert_eq!(longer.validate(), Ok(()));
+        assert_eq!(series.clone().with_data(&[0, 1], &[1, 2]).yerr(), None);
+
+        let mut mismatched = series;
+        mismatched.yerr = Some(vec![0.1]);
+        let err = mismatched.validate().unwrap_err();
+        assert_eq!(err, LengthMismatch::YErr { y: 3, yerr: 1 });
+        assert_eq!(err.to_string(), "y and yerr have different lengths (3 and 1)");
+    }
+
+    #[test]
+    fn separate_axes_test() {
+        let series = Series::default().with_y(&[1, 4, 9]);
+        assert_eq!(series.validate(), Err(LengthMismatch::Axes { x: 0, y: 3 }));
+        let series = series.with_x(&[0.5, 1.0, 1.5]);
+        assert_eq!(series.validate(), Ok(()));
+        assert_eq!(series.into_data(), vec![[0.5, 1.0], [1.0, 4.0], [1.5, 9.0]]);
+    }
+
+    #[test]
+    #[should_panic(expected = "x and y have different lengths (2 and 1)")]
+    fn mismatched_axes_test() {
+        let series = Series::default().with_x(&[0, 1]).with_y(&[1]);
+        crate::figure::FigureBuilder::default().with_series(series).build();
+    }
+
+    #[test]
+    fn from_vec_test() {
+        assert_eq!(Series::from(vec![3.0, 1.0, 2.0]), Series::new(&[0, 1, 2], &[3.0, 1.0, 2.0]));
+        let series = Series::from((vec![0.5, 1.5], vec![2.0, 4.0]));
+        assert_eq!(series, Series::new(&[0.5, 1.5], &[2.0, 4.0]));
+    }
+
+    #[test]
+    #[should_panic(expected = "x and y have different lengths")]
+    fn from_mismatched_vecs_test() {
+        let _ = Series::from((vec![0.0, 1.0], vec![1.0]));
+    }
+
+    #[test]
+    #[should_panic(expected = "x and y have different lengths")]
+    fn strict_data_test() {
+        Series::new(&[0, 1], &[1]);
+    }
+
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn ndarray_test() {
+        let y = ndarray::Array1::from(vec![1.0, 4.0, 9.0]);
+        let expected = Series::new(&[0.0, 1.0, 2.0], &[1.0, 4.0, 9.0]);
+        assert_eq!(Series::from_ndarray(&y), expected);
+        assert_eq!(Series::from(&y.view()), expected);
+    }
+
+    #[cfg(feature = "nalgebra")]
+    #[test]
+    fn nalgebra_test() {
+        let y = nalgebra::DVector::from_vec(vec![1.0, 4.0, 9.0]);
+        let expected = Series::new(&[0.0, 1.0, 2.0], &[1.0, 4.0, 9.0]);
+        assert_eq!(Series::from_nalgebra(&y), expected);
+        assert_eq!(Series::from(&nalgebra::Vector3::new(1.0, 4.0, 9.0)), expected);
+    }
+}
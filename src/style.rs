@@ -1,10 +1,13 @@
 use csscolorparser as css;
+use std::fmt;
+use std::str::FromStr;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Stroke {
     curve: String,
     pub width: usize,
     pub dashed: bool,
+    dash_array: Option<usize>,
 }
 
 impl Stroke {
@@ -19,15 +22,32 @@ impl Stroke {
     pub fn curve(&self) -> &str {
         &self.curve
     }
+    /// ApexCharts curve name, `stepline` steps after each point and `linestep` before it, so
+    /// `step-after` and `step-before` are accepted for them. Unknown curves fall back to smooth
     pub fn with_curve(mut self, curve: impl Into<String>) -> Self {
         let curve = curve.into();
         let curve = match curve.as_str() {
-            "smooth" | "straight" | "stepline" => curve,
+            "smooth" | "straight" | "stepline" | "linestep" | "monotoneCubic" => curve,
+            "step-after" => "stepline".to_string(),
+            "step-before" => "linestep".to_string(),
             _ => "smooth".to_string(),
         };
         self.curve = curve;
         self
     }
+    /// Length of dashes and gaps, `3 * width` for dashed strokes unless overridden
+    pub fn dash_array(&self) -> usize {
+        match self.dash_array {
+            Some(dash) => dash,
+            None if self.dashed => 3 * self.width,
+            None => 0,
+        }
+    }
+    /// Overrides the dash length derived from `dashed`, 0 draws a solid line
+    pub fn with_dash_array(mut self, dash: usize) -> Self {
+        self.dash_array = Some(dash);
+        self
+    }
 }
 
 impl Default for Stroke {
@@ -36,6 +56,7 @@ impl Default for Stroke {
             curve: "smooth".to_string(),
             width: 0,
             dashed: false,
+            dash_array: None,
         }
     }
 }
@@ -71,10 +92,18 @@ impl Marker {
     pub fn shape(&self) -> &str {
         &self.shape
     }
+    /// Shape as drawn by ApexCharts, which has all four shapes built in
+    pub fn apex_shape(&self) -> &str {
+        match self.shape.as_str() {
+            shape @ ("square" | "triangle" | "diamond") => shape,
+            _ => "circle",
+        }
+    }
+    /// One of `circle`, `square`, `triangle` or `diamond`, anything else falls back to circle
     pub fn with_shape(mut self, shape: impl Into<String>) -> Self {
         let shape = shape.into();
         let shape = match shape.as_str() {
-            "circle" | "square" => shape,
+            "circle" | "square" | "triangle" | "diamond" => shape,
             _ => "circle".to_string(),
         };
         self.shape = shape;
@@ -98,10 +127,23 @@ impl<S: Into<String>> From<(S, usize, bool)> for Marker {
     }
 }
 
+/// Color of a series, resolved against the figure palette when it is built
+#[derive(Default, Debug, Clone, PartialEq)]
+pub(crate) enum Color {
+    /// Hex code of a parsed css color
+    Fixed(String),
+    PaletteIndex(usize),
+    /// Next color of the figure's automatic color cycle
+    #[default]
+    Auto,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Style {
     typ: String,
-    color: Option<String>,
+    color: Color,
+    fill_opacity: Option<f64>,
+    stroke_opacity: Option<f64>,
     pub stroke: Stroke,
     pub marker: Marker,
 }
@@ -109,8 +151,10 @@ pub struct Style {
 impl Default for Style {
     fn default() -> Self {
         Self {
-            color: None,
+            color: Color::Auto,
             typ: "line".to_string(),
+            fill_opacity: None,
+            stroke_opacity: None,
             stroke: Stroke::default(),
             marker: Marker::default(),
         }
@@ -131,12 +175,82 @@ impl Style {
             .with_marker(marker)
     }
 
+    /// Line series drawn as filled circles of `size`, at least 1, without the connecting line
+    pub fn points_only(size: usize) -> Self {
+        Style::default()
+            .with_stroke(("smooth", 0, false))
+            .with_marker(("circle", size.max(1), true))
+    }
+
     pub fn color(&self) -> Option<&str> {
-        self.color.as_deref()
+        match &self.color {
+            Color::Fixed(color) => Some(color),
+            _ => None,
+        }
     }
 
+    /// Invalid css colors fall back to the automatic color
     pub fn with_color(mut self, color: impl Into<String>) -> Self {
-        self.color = css::parse(&color.into()).map(|c| c.to_hex_string()).ok();
+        self.color = match css::parse(&color.into()) {
+            Ok(color) => Color::Fixed(color.to_hex_string()),
+            Err(_) => Color::Auto,
+        };
+        self
+    }
+
+    pub fn palette_color(&self) -> Option<usize> {
+        match self.color {
+            Color::PaletteIndex(index) => Some(index),
+            _ => None,
+        }
+    }
+
+    /// Uses the color at `index` of the figure's palette, resolved when the figure is built
+    pub fn with_palette_color(mut self, index: usize) -> Self {
+        self.color = Color::PaletteIndex(index);
+        self
+    }
+
+    /// Color the series is drawn with given the figure's palette, with the alpha channel of
+    /// fixed colors dropped. Series with an automatic color get `None` and take the next color
+    /// of the figure's cycle instead
+    pub fn to_apex_color(&self, palette: &[&str]) -> Option<String> {
+        match &self.color {
+            Color::Fixed(_) => self.opaque_color(),
+            Color::PaletteIndex(index) if !palette.is_empty() => {
+                Some(palette[index % palette.len()].to_string())
+            }
+            _ => None,
+        }
+    }
+
+    /// Color with its alpha channel dropped, the alpha is applied through `stroke_opacity`
+    pub fn opaque_color(&self) -> Option<String> {
+        let color = css::parse(self.color()?).ok()?;
+        let (r, g, b, _) = color.rgba_u8();
+        Some(format!("#{r:02x}{g:02x}{b:02x}"))
+    }
+
+    pub fn fill_opacity(&self) -> Option<f64> {
+        self.fill_opacity
+    }
+
+    /// Opacity of the area or column fill, clamped to `0.0..=1.0`
+    pub fn with_fill_opacity(mut self, opacity: f64) -> Self {
+        self.fill_opacity = Some(opacity.clamp(0.0, 1.0));
+        self
+    }
+
+    /// Line opacity, either set explicitly or taken from the alpha channel of the color. An
+    /// explicit opacity takes precedence over the color alpha
+    pub fn stroke_opacity(&self) -> Option<f64> {
+        let alpha = || css::parse(self.color()?).ok().map(|c| c.a);
+        self.stroke_opacity.or_else(|| alpha().filter(|&a| a < 1.0))
+    }
+
+    /// Opacity of the line, clamped to `0.0..=1.0`
+    pub fn with_stroke_opacity(mut self, opacity: f64) -> Self {
+        self.stroke_opacity = Some(opacity.clamp(0.0, 1.0));
         self
     }
 
@@ -144,6 +258,11 @@ impl Style {
         &self.typ
     }
 
+    /// Line series drawn with markers only
+    pub fn is_scatter(&self) -> bool {
+        self.typ == "line" && self.stroke.width == 0
+    }
+
     pub fn with_typ(mut self, typ: impl Into<String>) -> Self {
         let typ = typ.into();
         self.typ = match typ.as_str() {
@@ -166,15 +285,18 @@ impl Style {
 
 impl From<Style> for String {
     fn from(s: Style) -> Self {
-        let mut color = String::new();
-        if let Some(c) = &s.color {
-            color.push_str(c);
+        let color = match &s.color {
+            Color::Fixed(c) => c.clone(),
+            Color::PaletteIndex(index) => format!("p{index}"),
+            Color::Auto => String::new(),
         };
 
         let mut stroke = match s.stroke.curve() {
             "smooth" => "~",
             "stepline" => "-",
+            "linestep" => "_",
             "straight" => "/",
+            "monotoneCubic" => ":",
             _ => unreachable!(),
         }
         .repeat(s.stroke.dashed as usize + 1);
@@ -185,6 +307,10 @@ impl From<Style> for String {
             ("circle", false) => ">",
             ("square", true) => ",",
             ("square", false) => "<",
+            ("triangle", true) => "^",
+            ("triangle", false) => "&",
+            ("diamond", true) => "*",
+            ("diamond", false) => "+",
             _ => unreachable!(),
         }
         .to_string();
@@ -208,13 +334,71 @@ impl From<String> for Style {
 }
 
 impl From<&str> for Style {
+    /// Decodes the style leniently, unknown sigils are skipped and invalid numbers or colors
+    /// fall back to their defaults
     fn from(style: &str) -> Self {
+        Style::decode(style, false).unwrap_or_default()
+    }
+}
+
+// `TryFrom<&str>` is taken by the blanket impl over `From<&str>`, so strict decoding goes
+// through `str::parse` instead
+impl FromStr for Style {
+    type Err = ParseStyleError;
+
+    fn from_str(style: &str) -> Result<Self, Self::Err> {
+        Style::decode(style, true)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseStyleError {
+    UnknownSigil(char, usize),
+    InvalidNumber(String),
+    InvalidColor(String),
+}
+
+impl fmt::Display for ParseStyleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownSigil(c, pos) => write!(f, "unknown style sigil '{c}' at {pos}"),
+            Self::InvalidNumber(digits) => write!(f, "style number '{digits}' is too large"),
+            Self::InvalidColor(color) => write!(f, "invalid style color '{color}'"),
+        }
+    }
+}
+
+impl std::error::Error for ParseStyleError {}
+
+impl Style {
+    // Tokens are read left to right, later ones overriding earlier ones:
+    //   type    '@' area or '%' column, line otherwise
+    //   color   one of the letter shortcuts, 'p' with an optional palette index (default 0),
+    //           '#' followed by a hex code or '$' followed by a css function up to its ')', an
+    //           unclosed function ends right after its '(' and is an invalid color
+    //   stroke  a curve sigil, doubled for dashes, then an optional width (default 2)
+    //   marker  a shape sigil then an optional size (default 4)
+    // A '#' color takes every ascii letter and digit after it, so a shortcut right after a hex
+    // code is never read on its own, "#f00e" is a 4 digit code and "#f00r" an invalid one.
+    // Strict decoding rejects what the lenient one skips or replaces with defaults
+    fn decode(style: &str, strict: bool) -> Result<Style, ParseStyleError> {
         let chars: Vec<char> = style.chars().collect();
         let (mut i, len) = (0, chars.len());
 
         let trailing = |idx: usize, f: fn(c: char) -> bool| -> String {
             chars[idx..].iter().take_while(|&c| f(*c)).collect()
         };
+        let number = |digits: &str, default: usize| match digits.parse() {
+            Ok(n) => Ok(n),
+            Err(_) if strict && !digits.is_empty() => {
+                Err(ParseStyleError::InvalidNumber(digits.to_string()))
+            }
+            Err(_) => Ok(default),
+        };
+        let color = |style: Style, color: String| match css::parse(&color) {
+            Err(_) if strict => Err(ParseStyleError::InvalidColor(color)),
+            _ => Ok(style.with_color(color)),
+        };
 
         let mut style = Style::default();
         while i < len {
@@ -231,49 +415,76 @@ impl From<&str> for Style {
                 'o' => style.with_color("orange"),
                 'k' => style.with_color("black"),
                 'w' => style.with_color("white"),
+                'e' => style.with_color("gray"),
+                'u' => style.with_color("purple"),
+
+                'p' => {
+                    let digits = trailing(i + 1, |d| d.is_ascii_digit());
+                    i += digits.len();
+                    style.with_palette_color(number(&digits, 0)?)
+                }
 
                 '#' => {
                     let digits = trailing(i + 1, |d| d.is_ascii_alphanumeric());
                     i += digits.len();
-                    style.with_color(format!("#{digits}"))
+                    color(style, format!("#{digits}"))?
                 }
 
-                '~' | '-' | '/' => {
+                '$' => {
+                    let name = trailing(i + 1, |c| c.is_ascii_alphabetic());
+                    let mut end = i + 1 + name.len();
+                    if end < len && chars[end] == '(' {
+                        let close = chars[end..].iter().position(|&c| c == ')');
+                        end = close.map_or(end + 1, |close| end + close + 1);
+                    }
+                    let function: String = chars[i + 1..end].iter().collect();
+                    i = end - 1;
+                    color(style, function)?
+                }
+
+                '~' | '-' | '_' | '/' | ':' => {
                     let repeated = i + 1 < len && chars[i + 1] == chars[i];
                     i += repeated as usize;
 
                     let digits = trailing(i + 1, |d| d.is_ascii_digit());
-                    let width = digits.parse().unwrap_or(2);
+                    let width = number(&digits, 2)?;
 
                     let curve = match chars[i] {
                         '~' => "smooth",
                         '-' => "stepline",
+                        '_' => "linestep",
                         '/' => "straight",
+                        ':' => "monotoneCubic",
                         _ => unreachable!(),
                     };
                     i += digits.len();
                     style.with_stroke((curve, width, repeated))
                 }
 
-                '.' | '>' | ',' | '<' => {
+                '.' | '>' | ',' | '<' | '^' | '&' | '*' | '+' => {
                     let digits = trailing(i + 1, |d| d.is_ascii_digit());
-                    let size = digits.parse().unwrap_or(4);
+                    let size = number(&digits, 4)?;
 
                     let (shape, filled) = match chars[i] {
                         '.' => ("circle", true),
                         '>' => ("circle", false),
                         ',' => ("square", true),
                         '<' => ("square", false),
+                        '^' => ("triangle", true),
+                        '&' => ("triangle", false),
+                        '*' => ("diamond", true),
+                        '+' => ("diamond", false),
                         _ => unreachable!(),
                     };
                     i += digits.len();
                     style.with_marker((shape, size, filled))
                 }
+                c if strict => return Err(ParseStyleError::UnknownSigil(c, i)),
                 _ => style,
             };
             i += 1;
         }
-        style
+        Ok(style)
     }
 }
 
@@ -288,6 +499,15 @@ macro_rules! style {
 mod tests {
     use super::*;
 
+    #[test]
+    fn points_only_test() {
+        let style = Style::points_only(8);
+        assert!(style.is_scatter());
+        assert_eq!(String::from(style.clone()), "~0.8");
+        assert_eq!(Style::from(String::from(style.clone())), style);
+        assert_eq!(Style::points_only(0).marker.size, 1);
+    }
+
     #[test]
     fn decode_nonsense() {
         let default = Style::default();
@@ -301,4 +521,174 @@ mod tests {
                 .with_typ("area")
         );
     }
+
+    #[test]
+    fn decode_functional_color() {
+        let s = style!("$rgb(255, 0, 0).6");
+        assert_eq!(s.color(), Some("#ff0000"));
+        assert_eq!(s.marker, Marker::new("circle", 6, true));
+
+        let s = style!("@$hsl(120, 100%, 25%)//");
+        assert_eq!(s.color(), Some("#008000"));
+        assert_eq!(s.typ(), "area");
+        assert_eq!(s.stroke, Stroke::new("straight", 2, true));
+
+        let s = style!("$rgba(0,0,255,0.5)");
+        assert_eq!(s.color(), Some("#0000ff80"));
+
+        let s = style!("$red.6");
+        assert_eq!(s.color(), Some("#ff0000"));
+        assert_eq!(s.marker, Marker::new("circle", 6, true));
+    }
+
+    #[test]
+    fn unclosed_functional_color_test() {
+        let s = style!("$rgb(255 ~4");
+        assert_eq!(s.color(), None);
+        assert_eq!(s.stroke, Stroke::new("smooth", 4, false));
+        assert_eq!(
+            Style::from_str("$rgb(255 ~4"),
+            Err(ParseStyleError::InvalidColor("rgb(".to_string()))
+        );
+    }
+
+    #[test]
+    fn dash_array_test() {
+        assert_eq!(Stroke::new("smooth", 2, false).dash_array(), 0);
+        assert_eq!(Stroke::new("smooth", 2, true).dash_array(), 6);
+        assert_eq!(Stroke::new("smooth", 2, true).with_dash_array(9).dash_array(), 9);
+        assert_eq!(Stroke::new("smooth", 2, false).with_dash_array(4).dash_array(), 4);
+    }
+
+    #[test]
+    fn decode_color_letters() {
+        assert_eq!(style!("e").color(), Some("#808080"));
+        assert_eq!(style!("u").color(), Some("#800080"));
+
+        let s = style!("p2.6");
+        assert_eq!((s.color(), s.palette_color()), (None, Some(2)));
+        assert_eq!(s.marker, Marker::new("circle", 6, true));
+        assert_eq!(style!("p").palette_color(), Some(0));
+        assert_eq!(style!("p3r").palette_color(), None);
+        assert_eq!(Style::from(String::from(s.clone())), s);
+    }
+
+    #[test]
+    fn decode_order_test() {
+        assert_eq!(style!("b~2"), Style::default().with_color("blue").with_stroke(("smooth", 2, false)));
+        assert_eq!(style!("~2b"), style!("b~2"));
+        assert_eq!(style!("#abc~2").color(), Some("#aabbcc"));
+        assert_eq!(style!("#abc~2").stroke, Stroke::new("smooth", 2, false));
+        assert_eq!(style!("12."), style!("."));
+        assert_eq!(style!("r#00f"), style!("#00f"));
+
+        // hex letters that are also shortcuts stay part of the code
+        assert_eq!(style!("#abce").color(), Some("#aabbccee"));
+        assert_eq!(style!("#0000ffbb.").color(), Some("#0000ffbb"));
+        assert_eq!(style!("#f00c").color(), Some("#ff0000cc"));
+        assert_eq!(style!("#f00 c").color(), Some("#00ffff"));
+        assert_eq!(style!("#f00r").color(), None);
+        assert_eq!(
+            Style::from_str("#f00r"),
+            Err(ParseStyleError::InvalidColor("#f00r".to_string()))
+        );
+    }
+
+    #[test]
+    fn apex_color_test() {
+        let palette = ["#008ffb", "#00e396", "#feb019"];
+        assert_eq!(style!("#ff000080").to_apex_color(&palette).as_deref(), Some("#ff0000"));
+        assert_eq!(style!("p4").to_apex_color(&palette).as_deref(), Some("#00e396"));
+        assert_eq!(style!("p4").to_apex_color(&[]), None);
+        assert_eq!(style!("~").to_apex_color(&palette), None);
+        assert_eq!(style!("#zz").color, Color::Auto);
+        assert_eq!(style!("rp1").color, Color::PaletteIndex(1));
+        assert_eq!(style!("p1r").color, Color::Fixed("#ff0000".to_string()));
+    }
+
+    #[test]
+    fn strict_decode_test() {
+        let s = Style::from_str("r.8/4@");
+        assert_eq!(s, Ok(style!("r.8/4@")));
+
+        let overflow = "~999999999999999999999";
+        assert_eq!(style!("~999999999999999999999"), style!("~"));
+        assert_eq!(
+            Style::from_str(overflow),
+            Err(ParseStyleError::InvalidNumber(overflow[1..].to_string()))
+        );
+        assert_eq!(
+            Style::from_str("r.8?"),
+            Err(ParseStyleError::UnknownSigil('?', 3))
+        );
+        assert_eq!(
+            Style::from_str("#zz"),
+            Err(ParseStyleError::InvalidColor("#zz".to_string()))
+        );
+        let err = Style::from_str("r x").unwrap_err();
+        assert_eq!(err.to_string(), "unknown style sigil ' ' at 1");
+    }
+
+    #[test]
+    fn fill_opacity_test() {
+        assert_eq!(Style::default().fill_opacity(), None);
+        assert_eq!(
+            Style::default().with_fill_opacity(0.4).fill_opacity(),
+            Some(0.4)
+        );
+        assert_eq!(
+            Style::default().with_fill_opacity(1.5).fill_opacity(),
+            Some(1.0)
+        );
+        assert_eq!(
+            Style::default().with_fill_opacity(-1.0).fill_opacity(),
+            Some(0.0)
+        );
+    }
+
+    #[test]
+    fn stroke_opacity_test() {
+        let s = Style::default().with_color("#ff000080");
+        assert!((s.stroke_opacity().unwrap() - 0.5).abs() < 0.01);
+        assert_eq!(s.opaque_color().as_deref(), Some("#ff0000"));
+        assert_eq!(s.with_stroke_opacity(0.2).stroke_opacity(), Some(0.2));
+
+        assert_eq!(style!("r").stroke_opacity(), None);
+        assert_eq!(Style::default().opaque_color(), None);
+    }
+
+    #[test]
+    fn marker_shape_test() {
+        for (code, shape, filled) in [
+            ("^", "triangle", true),
+            ("&", "triangle", false),
+            ("*", "diamond", true),
+            ("+", "diamond", false),
+        ] {
+            let s = Style::from(format!("r~3{code}6"));
+            assert_eq!(s.marker, Marker::new(shape, 6, filled));
+            assert_eq!(Style::from(String::from(s.clone())), s);
+        }
+        assert_eq!(Marker::new("diamond", 4, true).apex_shape(), "diamond");
+        assert_eq!(Marker::new("triangle", 4, true).apex_shape(), "triangle");
+        assert_eq!(Marker::new("star", 4, true).shape(), "circle");
+    }
+
+    #[test]
+    fn curve_test() {
+        for (code, curve, dashed) in [
+            ("_", "linestep", false),
+            ("__", "linestep", true),
+            ("-", "stepline", false),
+            (":", "monotoneCubic", false),
+            ("::", "monotoneCubic", true),
+        ] {
+            let s = Style::from(format!("r{code}3."));
+            assert_eq!(s.stroke, Stroke::new(curve, 3, dashed));
+            assert_eq!(Style::from(String::from(s.clone())), s);
+        }
+        assert_eq!(Stroke::new("step-before", 2, false).curve(), "linestep");
+        assert_eq!(Stroke::new("step-after", 2, false).curve(), "stepline");
+        assert_eq!(Stroke::new("monotoneCubic", 2, false).curve(), "monotoneCubic");
+    }
 }
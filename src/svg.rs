@@ -0,0 +1,232 @@
+use std::fmt::Write;
+
+use crate::series::Series;
+
+// Space around the plot area for the title and the axis labels
+const MARGIN_LEFT: f64 = 60.0;
+const MARGIN_RIGHT: f64 = 20.0;
+const MARGIN_TOP: f64 = 20.0;
+const MARGIN_BOTTOM: f64 = 40.0;
+const TITLE_HEIGHT: f64 = 30.0;
+
+// Coordinates are rounded to hundredths of a pixel, with trailing zeros dropped
+fn num(value: f64) -> String {
+    let text = format!("{value:.2}");
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    match text {
+        "-0" => "0".to_string(),
+        text => text.to_string(),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// Smallest and largest finite value, widened around a single value so it can be scaled
+fn range(values: impl Iterator<Item = f64>) -> (f64, f64) {
+    let (min, max) = values
+        .filter(|v| v.is_finite())
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), v| (min.min(v), max.max(v)));
+    match (min, max) {
+        (min, max) if min > max => (0.0, 1.0),
+        (min, max) if min == max => (min - 0.5, max + 0.5),
+        range => range,
+    }
+}
+
+struct Scale {
+    x: (f64, f64),
+    y: (f64, f64),
+    left: f64,
+    top: f64,
+    right: f64,
+    bottom: f64,
+}
+
+impl Scale {
+    fn point(&self, x: f64, y: f64) -> (f64, f64) {
+        let px = self.left + (x - self.x.0) / (self.x.1 - self.x.0) * (self.right - self.left);
+        let py = self.top + (self.y.1 - y) / (self.y.1 - self.y.0) * (self.bottom - self.top);
+        (px, py)
+    }
+}
+
+// Polyline through the points, each run of finite points starting a new subpath
+fn path(points: &[(f64, f64)], ys: &[f64], curve: &str) -> String {
+    let mut d = String::new();
+    let mut drawing = false;
+    for (&(x, y), value) in points.iter().zip(ys) {
+        if !value.is_finite() {
+            drawing = false;
+            continue;
+        }
+        let (x, y) = (num(x), num(y));
+        match (drawing, curve) {
+            (false, _) => write!(d, "M{x} {y}"),
+            (true, "stepline") => write!(d, "H{x}V{y}"),
+            (true, "linestep") => write!(d, "V{y}H{x}"),
+            (true, _) => write!(d, "L{x} {y}"),
+        }
+        .unwrap();
+        drawing = true;
+    }
+    d
+}
+
+// Shape centered on the point, `s` being half its width like a circle's radius
+fn marker(shape: &str, (x, y): (f64, f64), s: f64, paint: &str) -> String {
+    match shape {
+        "square" => format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{2}\" height=\"{2}\" {paint}/>",
+            num(x - s),
+            num(y - s),
+            num(2.0 * s)
+        ),
+        "triangle" => polygon(&[(x, y - s), (x + s, y + s), (x - s, y + s)], paint),
+        "diamond" => polygon(&[(x, y - s), (x + s, y), (x, y + s), (x - s, y)], paint),
+        _ => format!("<circle cx=\"{}\" cy=\"{}\" r=\"{}\" {paint}/>", num(x), num(y), num(s)),
+    }
+}
+
+fn polygon(points: &[(f64, f64)], paint: &str) -> String {
+    let points: Vec<_> = points.iter().map(|&(x, y)| format!("{},{}", num(x), num(y))).collect();
+    format!("<polygon points=\"{}\" {paint}/>", points.join(" "))
+}
+
+/// Renders series as a static svg with straight, or stepped, lines and markers. Smooth curves
+/// are drawn straight and areas and columns as lines, `ink` is the color of text and axes
+pub(crate) fn render(
+    title: Option<&str>,
+    (width, height): (usize, usize),
+    background: Option<&str>,
+    ink: &str,
+    series: &[(Series, &str)],
+) -> String {
+    let (width, height) = (width as f64, height as f64);
+    let top = MARGIN_TOP + if title.is_some() { TITLE_HEIGHT } else { 0.0 };
+    let scale = Scale {
+        x: range(series.iter().flat_map(|(ser, _)| ser.data()).map(|[x, _]| *x)),
+        y: range(series.iter().flat_map(|(ser, _)| ser.data()).map(|[_, y]| *y)),
+        left: MARGIN_LEFT,
+        top,
+        right: (width - MARGIN_RIGHT).max(MARGIN_LEFT + 1.0),
+        bottom: (height - MARGIN_BOTTOM).max(top + 1.0),
+    };
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\" font-family=\"sans-serif\" font-size=\"12\">\n",
+        num(width),
+        num(height)
+    );
+    if let Some(background) = background {
+        writeln!(svg, "<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>", escape(background)).unwrap();
+    }
+    if let Some(title) = title {
+        writeln!(
+            svg,
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\" font-size=\"16\" fill=\"{ink}\">{}</text>",
+            num(width / 2.0),
+            num(MARGIN_TOP + 4.0),
+            escape(title)
+        )
+        .unwrap();
+    }
+
+    let (left, right, top, bottom) = (scale.left, scale.right, scale.top, scale.bottom);
+    writeln!(
+        svg,
+        "<path d=\"M{} {}V{}H{}\" fill=\"none\" stroke=\"{ink}\"/>",
+        num(left),
+        num(top),
+        num(bottom),
+        num(right)
+    )
+    .unwrap();
+    let label = |x: f64, y: f64, anchor: &str, value: f64| {
+        format!(
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"{anchor}\" fill=\"{ink}\">{}</text>\n",
+            num(x),
+            num(y),
+            num(value)
+        )
+    };
+    svg += &label(left - 6.0, top + 4.0, "end", scale.y.1);
+    svg += &label(left - 6.0, bottom + 4.0, "end", scale.y.0);
+    svg += &label(left, bottom + 18.0, "middle", scale.x.0);
+    svg += &label(right, bottom + 18.0, "middle", scale.x.1);
+
+    for (ser, color) in series {
+        let color = escape(color);
+        let style = &ser.style;
+        let data = ser.data();
+        let points: Vec<_> = data.iter().map(|[&x, &y]| scale.point(x, y)).collect();
+        let ys: Vec<_> = data.iter().map(|[_, &y]| y).collect();
+
+        svg += "<g>\n";
+        if style.stroke.width > 0 {
+            let mut attributes = format!(
+                "fill=\"none\" stroke=\"{color}\" stroke-width=\"{}\" stroke-linecap=\"square\"",
+                style.stroke.width
+            );
+            if style.stroke.dash_array() > 0 {
+                write!(attributes, " stroke-dasharray=\"{}\"", style.stroke.dash_array()).unwrap();
+            }
+            if let Some(opacity) = style.stroke_opacity() {
+                write!(attributes, " stroke-opacity=\"{}\"", num(opacity)).unwrap();
+            }
+            let d = path(&points, &ys, style.stroke.curve());
+            writeln!(svg, "<path d=\"{d}\" {attributes}/>").unwrap();
+        }
+
+        let size = style.marker.size as f64;
+        let paint = |color: &str| match style.marker.filled {
+            true => format!("fill=\"{color}\""),
+            false => format!("fill=\"none\" stroke=\"{color}\""),
+        };
+        let point_colors = ser.point_colors().filter(|_| style.is_scatter());
+        let skipped = match ser.endpoint_marker() {
+            true => points.len().saturating_sub(1),
+            false => 0,
+        };
+        if size > 0.0 {
+            for (i, (&point, y)) in points.iter().zip(&ys).enumerate().skip(skipped) {
+                if y.is_finite() {
+                    let paint = match point_colors.and_then(|colors| colors.get(i)) {
+                        Some(point_color) => paint(&escape(point_color)),
+                        None => paint(&color),
+                    };
+                    svg += &marker(style.marker.shape(), point, size, &paint);
+                    svg += "\n";
+                }
+            }
+        }
+        svg += "</g>\n";
+    }
+    svg += "</svg>\n";
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_test() {
+        let points = [(0.0, 0.0), (1.0, 1.0), (2.0, 2.0), (3.0, 3.0)];
+        let ys = [0.0, f64::NAN, 2.0, 3.0];
+        assert_eq!(path(&points, &ys, "straight"), "M0 0M2 2L3 3");
+        assert_eq!(path(&points, &ys, "stepline"), "M0 0M2 2H3V3");
+        assert_eq!(path(&points, &ys, "linestep"), "M0 0M2 2V3H3");
+    }
+
+    #[test]
+    fn num_test() {
+        assert_eq!(num(1.0 / 3.0), "0.33");
+        assert_eq!(num(-0.001), "0");
+        assert_eq!(num(260.0), "260");
+    }
+}
@@ -1,103 +1,545 @@
 use image::buffer::ConvertBuffer;
 use image::{
-    DynamicImage, GrayAlphaImage, GrayImage, Rgb32FImage, RgbImage, Rgba32FImage, RgbaImage, Pixel
+    DynamicImage, GrayAlphaImage, GrayImage, Rgb32FImage, RgbImage, Rgba32FImage, RgbaImage, Pixel,
+    ImageError,
 };
+use image::imageops::FilterType;
 use image::{ImageBuffer, Luma, LumaA, Rgb, Rgba};
 
+use std::fmt;
+use std::str::FromStr;
+
+use crate::text;
+
 type Rgb16Image = ImageBuffer<Rgb<u16>, Vec<u16>>;
 type Rgba16Image = ImageBuffer<Rgba<u16>, Vec<u16>>;
 type Gray16Image = ImageBuffer<Luma<u16>, Vec<u16>>;
 type GrayAlpha16Image = ImageBuffer<LumaA<u16>, Vec<u16>>;
 type Gray32fImage = ImageBuffer<Luma<f32>, Vec<f32>>;
 type GrayAlpha32fImage = ImageBuffer<LumaA<f32>, Vec<f32>>;
+type Gray16sImage = ImageBuffer<Luma<i16>, Vec<i16>>;
+type Gray32uImage = ImageBuffer<Luma<u32>, Vec<u32>>;
+type Luma64Image = ImageBuffer<Luma<f64>, Vec<f64>>;
 
-pub trait WebImage  {
-    fn encode64(&self) -> String;
-    fn colormap(&self, cm: &str) -> RgbImage;
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub enum WebFormat {
+    #[default]
+    Png,
+    /// Lossy jpeg with quality in range 1..=100, alpha channel is dropped
+    Jpeg(u8),
+    /// Lossy webp with quality in range 0..=100, requires the `webp` feature. Without it
+    /// encoding fails with `ImageError::Unsupported` and figures fall back to png
+    WebP(u8),
+}
+
+impl WebFormat {
+    /// Format that figures are actually encoded with, unsupported formats fall back to png
+    pub fn effective(self) -> WebFormat {
+        match self {
+            WebFormat::WebP(_) if !cfg!(feature = "webp") => WebFormat::Png,
+            format => format,
+        }
+    }
+
+    pub fn mime(&self) -> &'static str {
+        match self.effective() {
+            WebFormat::Png => "image/png",
+            WebFormat::Jpeg(_) => "image/jpeg",
+            WebFormat::WebP(_) => "image/webp",
+        }
+    }
 }
 
 macro_rules! encode {
-    ($img:expr) => {{
+    ($img:expr) => {
+        encode!($img, image::ImageOutputFormat::Png)
+    };
+    ($img:expr, $format:expr) => {{
         let mut buf: Vec<u8> = Vec::new();
-        $img.write_to(&mut std::io::Cursor::new(&mut buf), image::ImageOutputFormat::Png).unwrap();
-        base64::encode(buf)
+        $img.write_to(&mut std::io::Cursor::new(&mut buf), $format)
+            .map(|_| base64::encode(buf))
     }};
 }
 
-macro_rules! recolor {
-    ($img:expr, $cm:expr) => {{
-        let gradient = match $cm {
-            "br_bg" => Some(colorgrad::br_bg()),
-            "pr_gn" => Some(colorgrad::pr_gn()),
-            "pi_yg" => Some(colorgrad::pi_yg()),
-            "pu_or" => Some(colorgrad::pu_or()),
-            "rd_bu" => Some(colorgrad::rd_bu()),
-            "rd_gy" => Some(colorgrad::rd_gy()),
-            "rd_yl_bu" => Some(colorgrad::rd_yl_bu()),
-            "rd_yl_gn" => Some(colorgrad::rd_yl_gn()),
-            "spectral" => Some(colorgrad::spectral()),
-            "blues" => Some(colorgrad::blues()),
-            "greens" => Some(colorgrad::greens()),
-            "greys" => Some(colorgrad::greys()),
-            "oranges" => Some(colorgrad::oranges()),
-            "purples" => Some(colorgrad::purples()),
-            "reds" => Some(colorgrad::reds()),
-            "turbo" => Some(colorgrad::turbo()),
-            "viridis" => Some(colorgrad::viridis()),
-            "inferno" => Some(colorgrad::inferno()),
-            "magma" => Some(colorgrad::magma()),
-            "plasma" => Some(colorgrad::plasma()),
-            "cividis" => Some(colorgrad::cividis()),
-            "warm" => Some(colorgrad::warm()),
-            "cool" => Some(colorgrad::cool()),
-            "cubehelix" => Some(colorgrad::cubehelix_default()),
-            "bu_gn" => Some(colorgrad::bu_gn()),
-            "bu_pu" => Some(colorgrad::bu_pu()),
-            "gn_bu" => Some(colorgrad::gn_bu()),
-            "or_rd" => Some(colorgrad::or_rd()),
-            "pu_bu_gn" => Some(colorgrad::pu_bu_gn()),
-            "pu_bu" => Some(colorgrad::pu_bu()),
-            "pu_rd" => Some(colorgrad::pu_rd()),
-            "rd_pu" => Some(colorgrad::rd_pu()),
-            "yl_gn_bu" => Some(colorgrad::yl_gn_bu()),
-            "yl_gn" => Some(colorgrad::yl_gn()),
-            "yl_or_br" => Some(colorgrad::yl_or_br()),
-            "yl_or_rd" => Some(colorgrad::yl_or_rd()),
-            "rainbow" => Some(colorgrad::rainbow()),
-            "sinebow" => Some(colorgrad::sinebow()),
-            _ => None,
+pub trait WebImage  {
+    fn try_encode64(&self) -> Result<String, ImageError>;
+
+    /// # Panics
+    /// When the image can't be encoded, see `try_encode64`
+    fn encode64(&self) -> String {
+        self.try_encode64().unwrap()
+    }
+
+    fn try_encode64_as(&self, format: WebFormat) -> Result<String, ImageError> {
+        match format {
+            WebFormat::Png => self.try_encode64(),
+            WebFormat::Jpeg(quality) => {
+                let rgb: RgbImage = self.to_rgba().convert();
+                encode!(rgb, image::ImageOutputFormat::Jpeg(quality))
+            }
+            #[cfg(feature = "webp")]
+            WebFormat::WebP(quality) => {
+                // `Encoder::encode` panics on images libwebp rejects, such as empty ones
+                let rgba = self.to_rgba();
+                let encoder = webp::Encoder::from_rgba(&rgba, rgba.width(), rgba.height());
+                match encoder.encode_simple(false, quality as f32) {
+                    Ok(webp) => Ok(base64::encode(&*webp)),
+                    Err(err) => Err(ImageError::Encoding(image::error::EncodingError::new(
+                        image::error::ImageFormatHint::Name("WebP".to_string()),
+                        format!("{err:?}"),
+                    ))),
+                }
+            }
+            #[cfg(not(feature = "webp"))]
+            WebFormat::WebP(_) => {
+                let webp = image::error::ImageFormatHint::Name("WebP".to_string());
+                let kind = image::error::UnsupportedErrorKind::Format(webp.clone());
+                Err(ImageError::Unsupported(image::error::UnsupportedError::from_format_and_kind(webp, kind)))
+            }
+        }
+    }
+
+    /// # Panics
+    /// When the image can't be encoded, see `try_encode64_as`
+    fn encode64_as(&self, format: WebFormat) -> String {
+        self.try_encode64_as(format).unwrap()
+    }
+
+    /// Same as `try_encode64` but first scales the image down with Lanczos3 filter, preserving
+    /// its aspect ratio, so that neither dimension exceeds `max`
+    fn try_encode64_max_dim(&self, max: u32) -> Result<String, ImageError> {
+        let rgba = self.to_rgba();
+        let (width, height) = rgba.dimensions();
+        if width <= max && height <= max {
+            return self.try_encode64();
+        }
+        downscale(&rgba, max).try_encode64()
+    }
+
+    /// # Panics
+    /// When the image can't be encoded, see `try_encode64_max_dim`
+    fn encode64_max_dim(&self, max: u32) -> String {
+        self.try_encode64_max_dim(max).unwrap()
+    }
+
+    /// Same as `try_encode64` but first blends transparent pixels over `background`, so that
+    /// they don't show as whatever the browser draws behind images
+    fn try_encode64_with_background(&self, background: Rgb<u8>) -> Result<String, ImageError> {
+        let rgba = self.to_rgba();
+        let blend = |src: u8, bg: u8, alpha: u8| {
+            let alpha = alpha as f64 / 255.0;
+            (src as f64 * alpha + bg as f64 * (1.0 - alpha)).round() as u8
         };
-        
-        if let Some(grad) = gradient {
-            let (mut max, mut min) = (f64::MIN, f64::MAX);
-            let buf: Vec<f64> = $img.pixels().map(|&px| {
-                let val = px.to_luma()[0] as f64;
-                if val > max { max = val }
-                if val < min { min = val }
-                val
-            }).collect();
-
-            let (width, height) = $img.dimensions();
-            RgbImage::from_fn(width, height, |x, y| {
-                let p = (buf[(x + y * width) as usize] - min) / max;
-                let (r, g, b, _) = grad.at(p).rgba_u8();
-                Rgb([r, g, b])
-            })
-        } else {
-            $img.convert()
+        let rgb = RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+            let [r, g, b, a] = rgba.get_pixel(x, y).0;
+            let [br, bg, bb] = background.0;
+            Rgb([blend(r, br, a), blend(g, bg, a), blend(b, bb, a)])
+        });
+        rgb.try_encode64()
+    }
+
+    /// # Panics
+    /// When the image can't be encoded, see `try_encode64_with_background`
+    fn encode64_with_background(&self, background: Rgb<u8>) -> String {
+        self.try_encode64_with_background(background).unwrap()
+    }
+
+    /// Pixels of the image as 8-bit rgba. By default the png from `encode64` is decoded, so
+    /// implementors only need to override it when they can convert directly
+    fn to_rgba(&self) -> RgbaImage {
+        let png = base64::decode(self.encode64()).expect("encode64 returns valid base64");
+        image::load_from_memory(&png).expect("encode64 returns a valid png").to_rgba8()
+    }
+
+    fn lumas(&self) -> Luma64Image;
+
+    /// Enlarges the image `factor` times by repeating pixels, which keeps small images crisp
+    /// where browsers would blur them when scaling up. Alpha is dropped
+    fn upscale_nearest(&self, factor: u32) -> RgbImage {
+        let rgb: RgbImage = self.to_rgba().convert();
+        let factor = factor.max(1);
+        let (width, height) = (rgb.width() * factor, rgb.height() * factor);
+        image::imageops::resize(&rgb, width, height, FilterType::Nearest)
+    }
+
+    fn colormap(&self, cm: ColorMap) -> RgbImage {
+        self.colormap_with_bounds(cm).0
+    }
+
+    /// Same as `colormap`, also returning the luminance range it was scaled to, e.g. for
+    /// labeling a `colorbar`
+    fn colormap_with_bounds(&self, cm: ColorMap) -> (RgbImage, f64, f64) {
+        let (min, max) = luma_range(&self.lumas());
+        (self.colormap_with_range(cm, min, max), min, max)
+    }
+
+    /// Colormaps luminance between `vmin` and `vmax`, values outside of the range are clamped
+    fn colormap_with_range(&self, cm: ColorMap, vmin: f64, vmax: f64) -> RgbImage {
+        self.colormap_clipped(cm, vmin, vmax, None, None)
+    }
+
+    /// Colormaps luminance between its `low` and `high` percentiles, which discards outliers
+    /// such as hot pixels. Percentiles are clamped to `0..=100`
+    fn colormap_percentile(&self, cm: ColorMap, low: f64, high: f64) -> RgbImage {
+        let (low, high) = (low.clamp(0.0, 100.0), high.clamp(0.0, 100.0));
+        assert!(low < high, "low percentile has to be smaller than high percentile");
+
+        let mut sorted = self.lumas().into_raw();
+        sorted.retain(|val| val.is_finite());
+        sorted.sort_by(f64::total_cmp);
+        let (vmin, vmax) = (percentile(&sorted, low), percentile(&sorted, high));
+        self.colormap_with_range(cm, vmin, vmax)
+    }
+
+    /// Same as `colormap_with_range` but paints values outside of the range with `under` and
+    /// `over` colors when those are given
+    fn colormap_clipped(
+        &self,
+        cm: ColorMap,
+        vmin: f64,
+        vmax: f64,
+        under: Option<Rgb<u8>>,
+        over: Option<Rgb<u8>>,
+    ) -> RgbImage {
+        paint(&self.lumas(), &Sampler::new(cm), vmin, vmax, under, over, BAD_COLOR)
+    }
+
+    /// Colormaps luminance into `levels` equal bands, each painted with the color at its center.
+    /// Zero levels fall back to the continuous colormap
+    fn colormap_discrete(&self, cm: ColorMap, levels: usize) -> RgbImage {
+        let lumas = self.lumas();
+        let (min, max) = luma_range(&lumas);
+        let grad = Sampler::new(cm).with_levels(levels);
+        paint(&lumas, &grad, min, max, None, None, BAD_COLOR)
+    }
+
+    /// Colormaps `log10(1 + val)` of each luminance, negative luminance is clamped to zero
+    /// before the transform so it maps to the lowest color
+    fn colormap_log(&self, cm: ColorMap) -> RgbImage {
+        let mut lumas = self.lumas();
+        for px in lumas.pixels_mut() {
+            let val = if px[0] < 0.0 { 0.0 } else { px[0] };
+            px[0] = val.ln_1p() / std::f64::consts::LN_10;
         }
-    }};
+        let (min, max) = luma_range(&lumas);
+        paint(&lumas, &Sampler::new(cm), min, max, None, None, BAD_COLOR)
+    }
+
+    /// Same as `colormap` but carries the alpha channel of each source pixel into the output,
+    /// fully transparent pixels are treated as missing data and do not affect the value range
+    fn colormap_rgba(&self, cm: ColorMap) -> RgbaImage {
+        let rgba = self.to_rgba();
+        let mut lumas = self.lumas();
+        for (px, src) in lumas.pixels_mut().zip(rgba.pixels()) {
+            if src[3] == 0 {
+                px[0] = f64::NAN;
+            }
+        }
+        let (min, max) = luma_range(&lumas);
+        let rgb = paint(&lumas, &Sampler::new(cm), min, max, None, None, BAD_COLOR);
+        RgbaImage::from_fn(rgb.width(), rgb.height(), |x, y| {
+            let [r, g, b] = rgb.get_pixel(x, y).0;
+            Rgba([r, g, b, rgba.get_pixel(x, y)[3]])
+        })
+    }
+
+    /// Same as `colormap` but paints NaN and infinite pixels with `bad` color instead of black
+    fn colormap_with_bad(&self, cm: ColorMap, bad: Rgb<u8>) -> RgbImage {
+        let lumas = self.lumas();
+        let (min, max) = luma_range(&lumas);
+        paint(&lumas, &Sampler::new(cm), min, max, None, None, bad)
+    }
+
+    /// Colormaps luminance with a gradient interpolated between at least two css color stops
+    fn colormap_custom(&self, stops: &[&str]) -> RgbImage {
+        match colorgrad::CustomGradient::new().html_colors(stops).build() {
+            Ok(grad) if stops.len() >= 2 => {
+                let lumas = self.lumas();
+                let (min, max) = luma_range(&lumas);
+                let grad = Sampler { grad, reversed: false, levels: None };
+                paint(&lumas, &grad, min, max, None, None, BAD_COLOR)
+            }
+            _ => self.to_rgba().convert(),
+        }
+    }
+}
+
+const BAD_COLOR: Rgb<u8> = Rgb([0, 0, 0]);
+
+pub(crate) fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = p / 100.0 * (sorted.len() - 1) as f64;
+    let (lo, hi) = (rank.floor() as usize, rank.ceil() as usize);
+    sorted[lo] + (sorted[hi] - sorted[lo]) * (rank - lo as f64)
+}
+
+fn luma_range(lumas: &Luma64Image) -> (f64, f64) {
+    lumas
+        .pixels()
+        .filter(|px| px[0].is_finite())
+        .fold((f64::MAX, f64::MIN), |(min, max), px| (min.min(px[0]), max.max(px[0])))
+}
+
+fn paint(
+    lumas: &Luma64Image,
+    grad: &Sampler,
+    vmin: f64,
+    vmax: f64,
+    under: Option<Rgb<u8>>,
+    over: Option<Rgb<u8>>,
+    bad: Rgb<u8>,
+) -> RgbImage {
+    let range = vmax - vmin;
+    RgbImage::from_fn(lumas.width(), lumas.height(), |x, y| {
+        let val = lumas.get_pixel(x, y)[0];
+        match (under, over) {
+            _ if !val.is_finite() => bad,
+            (Some(color), _) if val < vmin => color,
+            (_, Some(color)) if val > vmax => color,
+            _ => {
+                let p = if range > 0.0 { (val - vmin) / range } else { 0.0 };
+                grad.at(p.clamp(0.0, 1.0))
+            }
+        }
+    })
+}
+
+/// Renders a vertical strip of the colormap, with `vmax` color at the top and `vmin` color at
+/// the bottom
+pub fn colorbar(cm: ColorMap, width: u32, height: u32, vmin: f64, vmax: f64) -> RgbImage {
+    let step = (vmax - vmin) / height.saturating_sub(1).max(1) as f64;
+    let lumas = Luma64Image::from_fn(width, height, |_, y| Luma([vmax - step * y as f64]));
+    paint(&lumas, &Sampler::new(cm), vmin, vmax, None, None, BAD_COLOR)
+}
+
+/// Scales the image down with Lanczos3 filter so that neither dimension exceeds `max`,
+/// images that already fit are returned unchanged
+pub fn downscale(image: &RgbaImage, max: u32) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    if width <= max && height <= max {
+        return image.clone();
+    }
+    let scale = max as f64 / width.max(height) as f64;
+    let size = |dim: u32| ((dim as f64 * scale).round() as u32).max(1);
+    image::imageops::resize(image, size(width), size(height), FilterType::Lanczos3)
+}
+
+fn nice_length(target: f64) -> f64 {
+    let magnitude = 10f64.powi(target.log10().floor() as i32);
+    let mantissa = [5.0, 2.0, 1.0]
+        .into_iter()
+        .find(|&m| m * magnitude <= target)
+        .unwrap_or(1.0);
+    mantissa * magnitude
+}
+
+/// Draws a scale bar spanning roughly a fifth of the image width into its bottom right corner,
+/// labeled with the bar length rounded to 1, 2 or 5 times a power of ten.
+pub fn draw_scale_bar(img: &mut RgbaImage, pixels_per_unit: f64, unit: &str) {
+    let (width, height) = img.dimensions();
+    let length = nice_length(0.2 * width as f64 / pixels_per_unit);
+    let label = format!("{length} {unit}");
+
+    let scale = (height / 240).max(1);
+    let (margin, thickness) = (4 * scale, 2 * scale);
+    let bar = (length * pixels_per_unit).round() as u32;
+    let (text_w, text_h) = text::text_size(&label, scale);
+
+    let right = width.saturating_sub(margin);
+    let bottom = height.saturating_sub(margin);
+    let bar_top = bottom.saturating_sub(thickness);
+    let text_top = bar_top.saturating_sub(scale + text_h);
+
+    let (fg, bg) = (Rgba([255, 255, 255, 255]), Rgba([0, 0, 0, 255]));
+    let fill = |img: &mut RgbaImage, x0: u32, y0: u32, x1: u32, y1: u32, color| {
+        for y in y0..y1.min(height) {
+            for x in x0..x1.min(width) {
+                img.put_pixel(x, y, color);
+            }
+        }
+    };
+
+    let left = right.saturating_sub(bar.max(text_w).saturating_add(scale));
+    let top = text_top.saturating_sub(scale);
+    fill(img, left, top, right + scale, bottom + scale, bg);
+    fill(img, right.saturating_sub(bar), bar_top, right, bottom, fg);
+
+    let text_left = right.saturating_sub(text_w);
+    text::draw_text(img, text_left, text_top, scale, &label, fg);
+}
+
+// Gradient lookup that samples reversed colormaps exactly and optionally
+// quantizes positions into equal bands
+struct Sampler {
+    grad: colorgrad::Gradient,
+    reversed: bool,
+    levels: Option<usize>,
+}
+
+impl Sampler {
+    fn new(mut cm: ColorMap) -> Self {
+        let mut reversed = false;
+        while let ColorMap::Reversed(base) = cm {
+            (cm, reversed) = (*base, !reversed);
+        }
+        Sampler { grad: cm.gradient(), reversed, levels: None }
+    }
+
+    fn with_levels(self, levels: usize) -> Self {
+        let levels = (levels > 0).then_some(levels);
+        Sampler { levels, ..self }
+    }
+
+    fn at(&self, p: f64) -> Rgb<u8> {
+        let p = match self.levels {
+            Some(n) => ((p * n as f64).floor().min(n as f64 - 1.0) + 0.5) / n as f64,
+            None => p,
+        };
+        let p = if self.reversed { 1.0 - p } else { p };
+        let (r, g, b, _) = self.grad.at(p).rgba_u8();
+        Rgb([r, g, b])
+    }
+}
+
+macro_rules! color_maps {
+    ($($Variant:ident => $name:literal, $preset:path);* $(;)?) => {
+        /// Colormaps available for luminance coloring. Preset names are parsed from their snake
+        /// case form, e.g. `"rd_yl_bu"`, with an optional `_r` suffix for reversed maps
+        #[derive(Debug, Clone, PartialEq)]
+        pub enum ColorMap {
+            $($Variant,)*
+            /// Gradient interpolated evenly between the given colors
+            Custom(Vec<colorgrad::Color>),
+            Reversed(Box<ColorMap>),
+        }
+
+        impl ColorMap {
+            /// Underlying gradient, reversed maps are resampled in reverse order
+            pub fn gradient(&self) -> colorgrad::Gradient {
+                match self {
+                    $(ColorMap::$Variant => $preset(),)*
+                    ColorMap::Custom(colors) => {
+                        colorgrad::CustomGradient::new().colors(colors).build().unwrap()
+                    }
+                    ColorMap::Reversed(base) => {
+                        let mut colors = base.gradient().colors(256);
+                        colors.reverse();
+                        ColorMap::Custom(colors).gradient()
+                    }
+                }
+            }
+
+            fn preset(name: &str) -> Option<Self> {
+                match name {
+                    $($name => Some(ColorMap::$Variant),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+color_maps! {
+    BrBg => "br_bg", colorgrad::br_bg;
+    PrGn => "pr_gn", colorgrad::pr_gn;
+    PiYg => "pi_yg", colorgrad::pi_yg;
+    PuOr => "pu_or", colorgrad::pu_or;
+    RdBu => "rd_bu", colorgrad::rd_bu;
+    RdGy => "rd_gy", colorgrad::rd_gy;
+    RdYlBu => "rd_yl_bu", colorgrad::rd_yl_bu;
+    RdYlGn => "rd_yl_gn", colorgrad::rd_yl_gn;
+    Spectral => "spectral", colorgrad::spectral;
+    Blues => "blues", colorgrad::blues;
+    Greens => "greens", colorgrad::greens;
+    Greys => "greys", colorgrad::greys;
+    Oranges => "oranges", colorgrad::oranges;
+    Purples => "purples", colorgrad::purples;
+    Reds => "reds", colorgrad::reds;
+    Turbo => "turbo", colorgrad::turbo;
+    Viridis => "viridis", colorgrad::viridis;
+    Inferno => "inferno", colorgrad::inferno;
+    Magma => "magma", colorgrad::magma;
+    Plasma => "plasma", colorgrad::plasma;
+    Cividis => "cividis", colorgrad::cividis;
+    Warm => "warm", colorgrad::warm;
+    Cool => "cool", colorgrad::cool;
+    Cubehelix => "cubehelix", colorgrad::cubehelix_default;
+    BuGn => "bu_gn", colorgrad::bu_gn;
+    BuPu => "bu_pu", colorgrad::bu_pu;
+    GnBu => "gn_bu", colorgrad::gn_bu;
+    OrRd => "or_rd", colorgrad::or_rd;
+    PuBuGn => "pu_bu_gn", colorgrad::pu_bu_gn;
+    PuBu => "pu_bu", colorgrad::pu_bu;
+    PuRd => "pu_rd", colorgrad::pu_rd;
+    RdPu => "rd_pu", colorgrad::rd_pu;
+    YlGnBu => "yl_gn_bu", colorgrad::yl_gn_bu;
+    YlGn => "yl_gn", colorgrad::yl_gn;
+    YlOrBr => "yl_or_br", colorgrad::yl_or_br;
+    YlOrRd => "yl_or_rd", colorgrad::yl_or_rd;
+    Rainbow => "rainbow", colorgrad::rainbow;
+    Sinebow => "sinebow", colorgrad::sinebow;
+}
+
+impl FromStr for ColorMap {
+    type Err = ParseColorMapError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.strip_suffix("_r") {
+            Some(base) => Ok(ColorMap::Reversed(Box::new(base.parse()?))),
+            None => ColorMap::preset(name).ok_or_else(|| ParseColorMapError(name.to_string())),
+        }
+    }
+}
+
+impl TryFrom<&str> for ColorMap {
+    type Error = ParseColorMapError;
+
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        name.parse()
+    }
+}
+
+impl TryFrom<&String> for ColorMap {
+    type Error = ParseColorMapError;
+
+    fn try_from(name: &String) -> Result<Self, Self::Error> {
+        name.parse()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseColorMapError(String);
+
+impl fmt::Display for ParseColorMapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown colormap '{}'", self.0)
+    }
 }
 
+impl std::error::Error for ParseColorMapError {}
+
+
+macro_rules! lumas {
+    ($img:expr) => {{
+        let (width, height) = $img.dimensions();
+        let buf = $img.pixels().map(|px| px.to_luma()[0] as f64).collect();
+        Luma64Image::from_raw(width, height, buf).unwrap()
+    }};
+}
 
 macro_rules! impl_webimage  {
     ($($Image:ty),*) => {$(
         impl WebImage for $Image {
-            fn encode64(&self) -> String {
+            fn try_encode64(&self) -> Result<String, ImageError> {
                 encode!(self)
             }
-            fn colormap(&self, cm: &str) -> RgbImage {
-                recolor!(self, cm)
+            fn to_rgba(&self) -> RgbaImage {
+                self.convert()
+            }
+            fn lumas(&self) -> Luma64Image {
+                lumas!(self)
             }
         }
     )*};
@@ -106,11 +548,49 @@ macro_rules! impl_webimage  {
 macro_rules! impl_webimage_lossy {
     ($cast:ty; $($Image:ty),*) => {$(
         impl WebImage for $Image {
-            fn encode64(&self) -> String {
+            fn try_encode64(&self) -> Result<String, ImageError> {
                 encode!(self.convert() as $cast)
             }
-            fn colormap(&self, cm: &str) -> RgbImage {
-                recolor!(self, cm)
+            fn to_rgba(&self) -> RgbaImage {
+                self.convert()
+            }
+            fn lumas(&self) -> Luma64Image {
+                lumas!(self)
+            }
+        }
+    )*};
+}
+
+// Sample types the image crate can't convert, such as i16 depth maps or u32 label images, are
+// encoded by stretching the range of values in the image over the whole u16 range. Their
+// lumas keep the original values, so colormaps are scaled to the actual data
+fn stretched<T: image::Primitive + Into<f64>>(img: &ImageBuffer<Luma<T>, Vec<T>>) -> Gray16Image {
+    let values = img.pixels().map(|px| px[0].into());
+    let (min, max) = values.fold((f64::MAX, f64::MIN), |(min, max), v| (min.min(v), max.max(v)));
+    let range = max - min;
+    let (width, height) = img.dimensions();
+    Gray16Image::from_fn(width, height, |x, y| {
+        let p = match range > 0.0 {
+            true => (img.get_pixel(x, y)[0].into() - min) / range,
+            false => 0.0,
+        };
+        Luma([(p * u16::MAX as f64).round() as u16])
+    })
+}
+
+macro_rules! impl_webimage_stretched {
+    ($($Image:ty),*) => {$(
+        impl WebImage for $Image {
+            fn try_encode64(&self) -> Result<String, ImageError> {
+                encode!(stretched(self))
+            }
+            fn to_rgba(&self) -> RgbaImage {
+                stretched(self).convert()
+            }
+            fn lumas(&self) -> Luma64Image {
+                let (width, height) = self.dimensions();
+                let buf = self.pixels().map(|px| px[0].into()).collect();
+                Luma64Image::from_raw(width, height, buf).unwrap()
             }
         }
     )*};
@@ -119,7 +599,7 @@ macro_rules! impl_webimage_lossy {
 macro_rules! impl_webimage_dynamic {
     ($($Image:ty),*) => {$(
         impl WebImage for $Image {
-            fn encode64(&self) -> String {
+            fn try_encode64(&self) -> Result<String, ImageError> {
                 use DynamicImage::*;
                 match self {
                     ImageRgb32F(_) => encode!(self.to_rgb16()),
@@ -127,20 +607,23 @@ macro_rules! impl_webimage_dynamic {
                     _ => encode!(self),
                 }
             }
-            fn colormap(&self, cm: &str) -> RgbImage {
+            fn to_rgba(&self) -> RgbaImage {
+                DynamicImage::to_rgba8(self)
+            }
+            fn lumas(&self) -> Luma64Image {
                 use DynamicImage::*;
                 match self {
-                    ImageLuma8(img)   => recolor!(img, cm),
-                    ImageLumaA8(img)  => recolor!(img, cm),
-                    ImageRgb8(img)    => recolor!(img, cm),
-                    ImageRgba8(img)   => recolor!(img, cm),
-                    ImageLuma16(img)  => recolor!(img, cm),
-                    ImageLumaA16(img) => recolor!(img, cm),
-                    ImageRgb16(img)   => recolor!(img, cm),
-                    ImageRgba16(img)  => recolor!(img, cm),
-                    ImageRgb32F(img)  => recolor!(img, cm),
-                    ImageRgba32F(img) => recolor!(img, cm),
-                    _ => recolor!(self.to_luma8(), cm),
+                    ImageLuma8(img)   => lumas!(img),
+                    ImageLumaA8(img)  => lumas!(img),
+                    ImageRgb8(img)    => lumas!(img),
+                    ImageRgba8(img)   => lumas!(img),
+                    ImageLuma16(img)  => lumas!(img),
+                    ImageLumaA16(img) => lumas!(img),
+                    ImageRgb16(img)   => lumas!(img),
+                    ImageRgba16(img)  => lumas!(img),
+                    ImageRgb32F(img)  => lumas!(img),
+                    ImageRgba32F(img) => lumas!(img),
+                    _ => lumas!(self.to_luma8()),
                 }
             }
         }
@@ -157,6 +640,8 @@ impl_webimage_lossy!(Rgba16Image; Rgba32FImage, &Rgba32FImage);
 impl_webimage_lossy!(Gray16Image; Gray32fImage, &Gray32fImage);
 impl_webimage_lossy!(GrayAlpha16Image; GrayAlpha32fImage, &GrayAlpha32fImage);
 
+impl_webimage_stretched!(Gray16sImage, Gray32uImage, &Gray16sImage, &Gray32uImage);
+
 impl_webimage_dynamic!(DynamicImage, &DynamicImage);
 
 #[cfg(test)]
@@ -167,6 +652,24 @@ mod tests {
         DynamicImage::ImageLuma8(GrayImage::from_raw(1, 1, vec![0]).unwrap())
     }
 
+    #[test]
+    fn default_to_rgba_test() {
+        struct Checker;
+        impl WebImage for Checker {
+            fn try_encode64(&self) -> Result<String, ImageError> {
+                GrayImage::from_raw(2, 1, vec![0, 255]).unwrap().try_encode64()
+            }
+            fn lumas(&self) -> Luma64Image {
+                ImageBuffer::from_raw(2, 1, vec![0.0, 1.0]).unwrap()
+            }
+        }
+        let rgba = Checker.to_rgba();
+        assert_eq!(rgba.dimensions(), (2, 1));
+        assert_eq!(rgba.get_pixel(0, 0), &Rgba([0, 0, 0, 255]));
+        assert_eq!(rgba.get_pixel(1, 0), &Rgba([255, 255, 255, 255]));
+        assert_eq!(Checker.upscale_nearest(2).dimensions(), (4, 2));
+    }
+
     #[test]
     fn rgb_test() {
         let rgb8 = image().to_rgb8().encode64();
@@ -230,4 +733,344 @@ mod tests {
         assert_eq!(dyn_rgb32f, "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABEAIAAADA54+dAAAACklEQVR4nGOAAAAABwABTcTAjQAAAABJRU5ErkJggg==");
         assert_eq!(dyn_rgb32f_alpha, "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABEAYAAABPhRjKAAAADElEQVR4nGOAgP//AQMGAf/d+o2sAAAAAElFTkSuQmCC");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn scale_bar_test() {
+        let gray = Rgba([128, 128, 128, 255]);
+        let plain = RgbaImage::from_pixel(200, 100, gray);
+        let mut img = plain.clone();
+        draw_scale_bar(&mut img, 10.0, "µm");
+
+        // 20% of 200px at 10px per unit is 4 units, rounded down to a bar of 2 units
+        let white = Rgba([255, 255, 255, 255]);
+        assert_eq!(img.get_pixel(195, 95), &white);
+        assert_eq!(img.get_pixel(176, 95), &white);
+        assert_eq!(img.get_pixel(175, 95), &Rgba([0, 0, 0, 255]));
+        assert_eq!(img.get_pixel(0, 0), plain.get_pixel(0, 0));
+        assert_eq!(img.get_pixel(199, 99), plain.get_pixel(199, 99));
+
+        let (text_w, text_h) = text::text_size("2 µm", 1);
+        let mut label = RgbaImage::from_pixel(text_w, text_h, Rgba([0, 0, 0, 255]));
+        text::draw_text(&mut label, 0, 0, 1, "2 µm", white);
+        let (x0, y0) = (196 - text_w, 94 - 1 - text_h);
+        for (x, y, px) in label.enumerate_pixels() {
+            assert_eq!(img.get_pixel(x0 + x, y0 + y), px);
+        }
+
+        // Subnormal scales give a bar far wider than the image, clamped to its left edge
+        let mut img = plain.clone();
+        draw_scale_bar(&mut img, f64::MIN_POSITIVE / 4.0, "µm");
+        assert_eq!(img.get_pixel(0, 95), &white);
+    }
+
+    #[test]
+    fn clipped_colormap_test() {
+        let img = GrayImage::from_raw(3, 1, vec![0, 100, 250]).unwrap();
+        let (under, over) = (Rgb([0, 0, 255]), Rgb([255, 0, 0]));
+        let grad = ColorMap::Viridis.gradient();
+        let at = |p: f64| {
+            let (r, g, b, _) = grad.at(p).rgba_u8();
+            Rgb([r, g, b])
+        };
+
+        let clipped = img.colormap_clipped(ColorMap::Viridis, 50.0, 200.0, Some(under), Some(over));
+        assert_eq!(clipped.get_pixel(0, 0), &under);
+        assert_eq!(clipped.get_pixel(1, 0), &at(50.0 / 150.0));
+        assert_eq!(clipped.get_pixel(2, 0), &over);
+
+        let clamped = img.colormap_clipped(ColorMap::Viridis, 50.0, 200.0, None, None);
+        assert_eq!(clamped.get_pixel(0, 0), &at(0.0));
+        assert_eq!(clamped.get_pixel(2, 0), &at(1.0));
+    }
+
+    #[test]
+    fn colormap_normalization_test() {
+        let img = Gray16Image::from_raw(3, 1, vec![1000, 1500, 2000]).unwrap();
+        let mapped = img.colormap(ColorMap::Greys);
+        let grad = ColorMap::Greys.gradient();
+        let rgb = |p: f64| {
+            let (r, g, b, _) = grad.at(p).rgba_u8();
+            Rgb([r, g, b])
+        };
+        assert_eq!(mapped.get_pixel(0, 0), &rgb(0.0));
+        assert_eq!(mapped.get_pixel(1, 0), &rgb(0.5));
+        assert_eq!(mapped.get_pixel(2, 0), &rgb(1.0));
+
+        let flat = Gray16Image::from_pixel(2, 2, Luma([7])).colormap(ColorMap::Greys);
+        assert!(flat.pixels().all(|px| px == &rgb(0.0)));
+    }
+
+    #[test]
+    fn encode_format_test() {
+        let img = RgbImage::from_fn(64, 64, |x, y| Rgb([x as u8 * 4, y as u8 * 4, 128]));
+        assert_eq!(img.encode64_as(WebFormat::Png), img.encode64());
+
+        // base64 of the jpeg start of image marker
+        let jpeg = img.encode64_as(WebFormat::Jpeg(80));
+        assert!(jpeg.starts_with("/9j/"));
+    }
+
+    #[cfg(feature = "webp")]
+    #[test]
+    fn encode_webp_test() {
+        let img = RgbImage::from_fn(64, 64, |x, y| Rgb([x as u8 * 4, y as u8 * 4, 128]));
+        let webp = base64::decode(img.encode64_as(WebFormat::WebP(80))).unwrap();
+        assert_eq!(&webp[0..4], b"RIFF");
+        assert_eq!(&webp[8..12], b"WEBP");
+        assert_eq!(WebFormat::WebP(80).mime(), "image/webp");
+
+        let gradient = RgbImage::from_fn(256, 256, |x, y| Rgb([x as u8, y as u8, (x + y) as u8 / 2]));
+        let png = base64::decode(gradient.encode64()).unwrap();
+        let webp = base64::decode(gradient.encode64_as(WebFormat::WebP(80))).unwrap();
+        assert!(webp.len() < png.len());
+    }
+
+    #[cfg(not(feature = "webp"))]
+    #[test]
+    fn webp_fallback_test() {
+        let img = RgbImage::from_fn(8, 8, |x, y| Rgb([x as u8 * 32, y as u8 * 32, 128]));
+        assert_eq!(WebFormat::WebP(80).effective(), WebFormat::Png);
+        assert_eq!(WebFormat::WebP(80).mime(), "image/png");
+        let err = img.try_encode64_as(WebFormat::WebP(80)).unwrap_err();
+        assert!(matches!(err, ImageError::Unsupported(_)));
+    }
+
+    #[test]
+    fn custom_colormap_test() {
+        let ramp = GrayImage::from_fn(256, 1, |x, _| Luma([x as u8]));
+        let gray = ramp.colormap_custom(&["#000000", "#ffffff"]);
+        for (x, _, px) in gray.enumerate_pixels() {
+            let v = x as u8;
+            assert!(px.0.iter().all(|&c| c.abs_diff(v) <= 1));
+        }
+
+        let fallback: RgbImage = ramp.convert();
+        assert_eq!(ramp.colormap_custom(&["#000000"]), fallback);
+        assert_eq!(ramp.colormap_custom(&["#000000", "nope"]), fallback);
+    }
+
+    #[test]
+    fn reversed_colormap_test() {
+        let ramp = GrayImage::from_fn(16, 1, |x, _| Luma([x as u8 * 16]));
+        for cm in ["turbo", "spectral", "viridis"] {
+            let normal = ramp.colormap(cm.parse().unwrap());
+            let reversed = ramp.colormap(format!("{cm}_r").parse().unwrap());
+            assert_ne!(normal.get_pixel(0, 0), normal.get_pixel(15, 0));
+            assert_eq!(normal.get_pixel(0, 0), reversed.get_pixel(15, 0));
+            assert_eq!(normal.get_pixel(15, 0), reversed.get_pixel(0, 0));
+        }
+    }
+
+    #[test]
+    fn ranged_colormap_test() {
+        let dark = GrayImage::from_raw(2, 1, vec![0, 100]).unwrap();
+        let bright = GrayImage::from_raw(2, 1, vec![100, 255]).unwrap();
+        let dark = dark.colormap_with_range(ColorMap::Viridis, 0.0, 255.0);
+        let bright = bright.colormap_with_range(ColorMap::Viridis, 0.0, 255.0);
+        assert_eq!(dark.get_pixel(1, 0), bright.get_pixel(0, 0));
+
+        let img = GrayImage::from_raw(3, 1, vec![20, 100, 250]).unwrap();
+        let clamped = img.colormap_with_range(ColorMap::Viridis, 50.0, 200.0);
+        let low = img.colormap_with_range(ColorMap::Viridis, 20.0, 200.0);
+        let high = img.colormap_with_range(ColorMap::Viridis, 50.0, 250.0);
+        assert_eq!(clamped.get_pixel(0, 0), low.get_pixel(0, 0));
+        assert_eq!(clamped.get_pixel(2, 0), high.get_pixel(2, 0));
+        assert_eq!(img.colormap(ColorMap::Viridis), img.colormap_with_range(ColorMap::Viridis, 20.0, 250.0));
+    }
+
+    #[test]
+    fn stretched_test() {
+        let depth = Gray16sImage::from_raw(3, 1, vec![-1000, 0, 1000]).unwrap();
+        assert_eq!(depth.lumas().into_raw(), vec![-1000.0, 0.0, 1000.0]);
+        let (colormapped, min, max) = depth.colormap_with_bounds(ColorMap::Viridis);
+        assert_eq!((min, max), (-1000.0, 1000.0));
+        assert_eq!(colormapped, depth.colormap_with_range(ColorMap::Viridis, -1000.0, 1000.0));
+        assert_ne!(colormapped.get_pixel(0, 0), colormapped.get_pixel(2, 0));
+        assert_eq!(stretched(&depth).into_raw(), vec![0, 32768, u16::MAX]);
+
+        let labels = Gray32uImage::from_raw(2, 1, vec![7, 7]).unwrap();
+        assert_eq!(stretched(&labels).into_raw(), vec![0, 0]);
+        let decoded = image::load_from_memory(&base64::decode(labels.encode64()).unwrap());
+        assert_eq!(decoded.unwrap().into_luma16().into_raw(), vec![0, 0]);
+    }
+
+    #[test]
+    fn background_encode_test() {
+        let img = GrayAlphaImage::from_raw(2, 1, vec![0, 128, 40, 255]).unwrap();
+        let encoded = img.encode64_with_background(Rgb([255, 255, 255]));
+        let decoded = image::load_from_memory(&base64::decode(encoded).unwrap()).unwrap();
+        let decoded = decoded.as_rgb8().unwrap();
+        assert_eq!(decoded.get_pixel(0, 0), &Rgb([127, 127, 127]));
+        assert_eq!(decoded.get_pixel(1, 0), &Rgb([40, 40, 40]));
+    }
+
+    #[test]
+    fn upscale_nearest_test() {
+        let img = GrayImage::from_raw(2, 2, vec![0, 50, 100, 150]).unwrap();
+        let upscaled = img.upscale_nearest(3);
+        assert_eq!(upscaled.dimensions(), (6, 6));
+        for (x, y, px) in upscaled.enumerate_pixels() {
+            let luma = img.get_pixel(x / 3, y / 3)[0];
+            assert_eq!(px, &Rgb([luma; 3]));
+        }
+        assert_eq!(img.upscale_nearest(0).dimensions(), (2, 2));
+    }
+
+    #[test]
+    fn colormap_bounds_test() {
+        let img = GrayImage::from_raw(3, 1, vec![20, 100, 250]).unwrap();
+        let (colormapped, min, max) = img.colormap_with_bounds(ColorMap::Viridis);
+        assert_eq!((min, max), (20.0, 250.0));
+        assert_eq!(colormapped, img.colormap(ColorMap::Viridis));
+
+        let lumas = img.lumas();
+        assert_eq!(min, lumas.pixels().map(|px| px[0]).fold(f64::MAX, f64::min));
+        assert_eq!(max, lumas.pixels().map(|px| px[0]).fold(f64::MIN, f64::max));
+    }
+
+    #[test]
+    fn percentile_colormap_test() {
+        let mut img = Gray16Image::from_fn(101, 1, |x, _| Luma([x as u16]));
+        img.put_pixel(100, 0, Luma([60000]));
+
+        let auto = img.colormap(ColorMap::Greys);
+        let clipped = img.colormap_percentile(ColorMap::Greys, 0.0, 99.0);
+        let expected = img.colormap_with_range(ColorMap::Greys, 0.0, 99.0);
+        assert_eq!(clipped, expected);
+        assert_ne!(auto.get_pixel(99, 0), clipped.get_pixel(99, 0));
+        assert_eq!(clipped.get_pixel(99, 0), clipped.get_pixel(100, 0));
+
+        assert_eq!(percentile(&[1.0, 2.0, 3.0, 4.0], 50.0), 2.5);
+        assert_eq!(img.colormap_percentile(ColorMap::Greys, -10.0, 200.0), auto);
+    }
+
+    #[test]
+    #[should_panic]
+    fn percentile_order_test() {
+        GrayImage::new(1, 1).colormap_percentile(ColorMap::Greys, 90.0, 10.0);
+    }
+
+    #[test]
+    fn colorbar_test() {
+        let bar = colorbar(ColorMap::Viridis, 8, 64, 0.0, 10.0);
+        assert_eq!(bar.dimensions(), (8, 64));
+
+        let grad = Sampler::new(ColorMap::Viridis);
+        assert_eq!(*bar.get_pixel(0, 0), grad.at(1.0));
+        assert_eq!(*bar.get_pixel(7, 63), grad.at(0.0));
+        assert_eq!(bar.get_pixel(0, 20), bar.get_pixel(7, 20));
+    }
+
+    #[test]
+    fn non_finite_colormap_test() {
+        let mut img = Gray32fImage::from_fn(4, 1, |x, _| Luma([x as f32]));
+        img.put_pixel(1, 0, Luma([f32::NAN]));
+        img.put_pixel(2, 0, Luma([f32::INFINITY]));
+
+        let grad = Sampler::new(ColorMap::Viridis);
+        let cm = img.colormap(ColorMap::Viridis);
+        assert_eq!(*cm.get_pixel(0, 0), grad.at(0.0));
+        assert_eq!(*cm.get_pixel(3, 0), grad.at(1.0));
+        assert_eq!(*cm.get_pixel(1, 0), BAD_COLOR);
+        assert_eq!(*cm.get_pixel(2, 0), BAD_COLOR);
+
+        let bad = Rgb([255, 0, 255]);
+        let cm = img.colormap_with_bad(ColorMap::Viridis, bad);
+        assert_eq!(*cm.get_pixel(1, 0), bad);
+        assert_eq!(*cm.get_pixel(3, 0), grad.at(1.0));
+        assert_eq!(img.colormap_percentile(ColorMap::Viridis, 0.0, 100.0), img.colormap(ColorMap::Viridis));
+    }
+
+    #[test]
+    fn discrete_colormap_test() {
+        let img = GrayImage::from_fn(100, 1, |x, _| Luma([x as u8]));
+
+        let cm = img.colormap_discrete(ColorMap::Viridis, 5);
+        let mut colors = cm.pixels().collect::<Vec<_>>();
+        colors.dedup();
+        assert_eq!(colors.len(), 5);
+
+        let grad = Sampler::new(ColorMap::Viridis);
+        assert_eq!(*cm.get_pixel(0, 0), grad.at(0.1));
+        assert_eq!(*cm.get_pixel(99, 0), grad.at(0.9));
+        assert_eq!(img.colormap_discrete(ColorMap::Viridis, 0), img.colormap(ColorMap::Viridis));
+    }
+
+    #[test]
+    fn log_colormap_test() {
+        let ramp = [-5.0, 1.0, 10.0, 100.0, 1000.0];
+        let img = Gray32fImage::from_fn(5, 1, |x, _| Luma([ramp[x as usize] as f32]));
+        let cm = img.colormap_log(ColorMap::Greys);
+
+        let logs = ramp.map(|val: f64| (1.0 + val.max(0.0)).log10());
+        let grad = Sampler::new(ColorMap::Greys);
+        for (x, log) in logs.iter().enumerate() {
+            assert_eq!(*cm.get_pixel(x as u32, 0), grad.at(log / logs[4]));
+        }
+
+        let steps = logs.windows(2).skip(1).map(|w| (w[1] - w[0]) / logs[4]);
+        assert!(steps.into_iter().all(|step| (step - 0.3).abs() < 0.1));
+    }
+
+    #[test]
+    fn max_dim_test() {
+        let decode = |encoded: String| {
+            let bytes = base64::decode(encoded).unwrap();
+            image::load_from_memory(&bytes).unwrap()
+        };
+        let img = RgbImage::from_fn(100, 50, |x, y| Rgb([x as u8, y as u8, 0]));
+
+        assert_eq!(decode(img.encode64_max_dim(10)).to_rgba8().dimensions(), (10, 5));
+        assert_eq!(img.encode64_max_dim(100), img.encode64());
+        assert_eq!(downscale(&img.to_rgba(), 1).dimensions(), (1, 1));
+    }
+
+    #[test]
+    fn color_map_enum_test() {
+        assert_eq!("rd_yl_bu".parse(), Ok(ColorMap::RdYlBu));
+        assert_eq!(
+            "viridis_r".parse(),
+            Ok(ColorMap::Reversed(Box::new(ColorMap::Viridis)))
+        );
+        let err = "viridsi".parse::<ColorMap>().unwrap_err();
+        assert_eq!(err.to_string(), "unknown colormap 'viridsi'");
+
+        let img = GrayImage::from_fn(16, 1, |x, _| Luma([x as u8 * 16]));
+        assert_eq!(img.colormap(ColorMap::Turbo), img.colormap("turbo".parse().unwrap()));
+        assert!("nonsense_r".parse::<ColorMap>().is_err());
+
+        // Colormaps are taken by value, which keeps the trait usable as a trait object
+        let boxed: Box<dyn WebImage> = Box::new(img.clone());
+        assert_eq!(boxed.colormap(ColorMap::Turbo), img.colormap(ColorMap::Turbo));
+
+        let custom = ColorMap::Custom(vec![
+            colorgrad::Color::from_rgb(1.0, 0.0, 0.0),
+            colorgrad::Color::from_rgb(0.0, 0.0, 1.0),
+        ]);
+        let cm = img.colormap(custom.clone());
+        assert_eq!(*cm.get_pixel(0, 0), Rgb([255, 0, 0]));
+        assert_eq!(*cm.get_pixel(15, 0), Rgb([0, 0, 255]));
+
+        let reversed = ColorMap::Reversed(Box::new(custom)).gradient();
+        assert_eq!(reversed.at(0.0).rgba_u8(), (0, 0, 255, 255));
+        assert_eq!(reversed.at(1.0).rgba_u8(), (255, 0, 0, 255));
+    }
+
+    #[test]
+    fn rgba_colormap_test() {
+        let mut img = GrayAlpha16Image::from_fn(4, 1, |x, _| LumaA([x as u16 * 100, u16::MAX]));
+        img.put_pixel(1, 0, LumaA([60000, 0]));
+        img.put_pixel(2, 0, LumaA([200, u16::MAX / 2]));
+
+        let grad = Sampler::new(ColorMap::Viridis);
+        let cm = img.colormap_rgba(ColorMap::Viridis);
+        let at = |p, alpha| {
+            let [r, g, b] = grad.at(p).0;
+            Rgba([r, g, b, alpha])
+        };
+        assert_eq!(*cm.get_pixel(0, 0), at(0.0, 255));
+        assert_eq!(cm.get_pixel(1, 0)[3], 0);
+        assert_eq!(*cm.get_pixel(2, 0), at(2.0 / 3.0, 127));
+        assert_eq!(*cm.get_pixel(3, 0), at(1.0, 255));
+    }
+}